@@ -1,85 +1,433 @@
 pub mod category;
 pub mod code_point;
+pub mod entropy;
+pub mod exclusion;
+pub mod hash;
+pub mod length;
+pub mod normalization;
+pub mod passphrase;
+pub mod password_rules;
+pub mod policy;
+pub mod pool;
+pub mod position;
+pub mod preset;
+pub mod profile;
+pub mod pronounceable;
+pub mod regex;
+pub mod rules;
+pub mod script;
+pub mod settings;
+pub mod template;
 
 use arboard::Clipboard;
 
-use rand::seq::SliceRandom;
+use rand::{
+    distr::{Distribution, weighted::WeightedIndex},
+    seq::SliceRandom,
+};
 
-use passgen::{generate_random_chars_from_char_vec, generate_rng};
+use serde::{Deserialize, Serialize};
 
-use self::category::{CodePointCategory, generate_code_point_categories};
+use rand_chacha::ChaCha20Rng;
+
+use passgen::{
+    generate_random_chars_from_char_vec, generate_rng,
+    generate_weighted_random_chars_from_char_vecs, sample_capped_multinomial,
+};
+
+use self::{
+    category::{CodePointCategory, generate_code_point_categories},
+    exclusion::{ConfusableExclusion, UnicodeVersion},
+    hash::TargetHash,
+    length::LengthUnit,
+    normalization::NormalizationForm,
+    passphrase::PassphraseOptions,
+    pool::CharacterPool,
+    profile::{DEFAULT_PROFILE_NAME, Profile},
+    pronounceable::PronounceableOptions,
+    regex::DEFAULT_REGEX,
+    rules::RejectionRules,
+    script::RestrictionLevel,
+    template::{DEFAULT_TEMPLATE, generate_from_template},
+};
+
+const DEFAULT_PASSWORD_LENGTH: usize = 128;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SamplingMode {
+    // Every enabled code point is equally likely when filling the remaining length.
+    #[default]
+    Uniform,
+    // A category is picked by weight first, then a code point uniformly within it.
+    Weighted,
+}
+
+impl SamplingMode {
+    pub fn get_label(&self) -> &str {
+        match self {
+            SamplingMode::Uniform => "Uniform",
+            SamplingMode::Weighted => "Weighted",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GenerationMode {
+    // Random code points from the enabled categories.
+    #[default]
+    Characters,
+    // Random words from a wordlist, Diceware style.
+    Passphrase,
+    // Random syllables that can be read aloud.
+    Pronounceable,
+    // Literal text and placeholders that draw from named categories.
+    Template,
+    // Strings of the password length that fully match a regex.
+    Regex,
+}
+
+impl GenerationMode {
+    pub fn get_label(&self) -> &str {
+        match self {
+            GenerationMode::Characters => "Characters",
+            GenerationMode::Passphrase => "Passphrase",
+            GenerationMode::Pronounceable => "Pronounceable",
+            GenerationMode::Template => "Template",
+            GenerationMode::Regex => "Regex",
+        }
+    }
+}
 
 pub struct Config {
+    generation_mode: GenerationMode,
     password_length: usize,
-    clipboard: Clipboard,
+    target_entropy: Option<f64>,
+    sampling_mode: SamplingMode,
+    passphrase: PassphraseOptions,
+    pronounceable: PronounceableOptions,
+    template: String,
+    regex: String,
+    rules: RejectionRules,
+    confusables: ConfusableExclusion,
+    restriction_level: RestrictionLevel,
+    max_unicode_version: Option<UnicodeVersion>,
+    normalization: NormalizationForm,
+    case_fold_safe: bool,
+    length_unit: LengthUnit,
+    target_hash: TargetHash,
+    clipboard: Option<Clipboard>,
     pub categories: Vec<CodePointCategory>,
+    profiles: Vec<Profile>,
+    active_profile: usize,
 }
 
 impl Config {
     pub fn new() -> Self {
-        Config {
-            password_length: 128,
-            clipboard: Clipboard::new().unwrap(),
+        let mut config = Config {
+            generation_mode: GenerationMode::Characters,
+            password_length: DEFAULT_PASSWORD_LENGTH,
+            target_entropy: None,
+            sampling_mode: SamplingMode::Uniform,
+            passphrase: PassphraseOptions::default(),
+            pronounceable: PronounceableOptions::default(),
+            template: DEFAULT_TEMPLATE.to_string(),
+            regex: DEFAULT_REGEX.to_string(),
+            rules: RejectionRules::default(),
+            confusables: ConfusableExclusion::default(),
+            restriction_level: RestrictionLevel::default(),
+            max_unicode_version: None,
+            normalization: NormalizationForm::default(),
+            case_fold_safe: false,
+            length_unit: LengthUnit::default(),
+            target_hash: TargetHash::default(),
+            clipboard: None,
             categories: generate_code_point_categories(),
+            profiles: Vec::new(),
+            active_profile: 0,
+        };
+        config
+            .profiles
+            .push(Profile::new(DEFAULT_PROFILE_NAME, config.to_settings()));
+        config
+    }
+
+    pub fn reset_to_defaults(&mut self) {
+        self.generation_mode = GenerationMode::Characters;
+        self.password_length = DEFAULT_PASSWORD_LENGTH;
+        self.target_entropy = None;
+        self.sampling_mode = SamplingMode::Uniform;
+        self.passphrase = PassphraseOptions::default();
+        self.pronounceable = PronounceableOptions::default();
+        self.template = DEFAULT_TEMPLATE.to_string();
+        self.regex = DEFAULT_REGEX.to_string();
+        self.rules = RejectionRules::default();
+        self.confusables = ConfusableExclusion::default();
+        self.restriction_level = RestrictionLevel::default();
+        self.max_unicode_version = None;
+        self.normalization = NormalizationForm::default();
+        self.case_fold_safe = false;
+        self.length_unit = LengthUnit::default();
+        self.target_hash = TargetHash::default();
+        for cat in self.categories.iter_mut() {
+            cat.reset_to_defaults();
         }
+        self.update_exclusions();
     }
 
     pub fn get_all_categories(&self) -> &Vec<CodePointCategory> {
         &self.categories
     }
 
-    fn get_active_categories(&self) -> Vec<&CodePointCategory> {
+    pub fn get_categories_by_name_mut(
+        &mut self,
+        name: &str,
+    ) -> impl Iterator<Item = &mut CodePointCategory> {
+        self.categories
+            .iter_mut()
+            .filter(move |cat| cat.get_name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn get_active_categories(&self) -> Vec<&CodePointCategory> {
         self.categories
             .iter()
-            .filter(|cat| cat.is_enabled() && cat.get_active_code_points().len() > 0)
+            .filter(|cat| cat.is_enabled() && !cat.get_active_code_points().is_empty())
             .collect()
     }
 
-    fn get_active_chars_for_active_categories(&self) -> Vec<char> {
+    pub fn get_active_category_by_name(&self, name: &str) -> Option<&CodePointCategory> {
         self.get_active_categories()
-            .iter()
-            .flat_map(|cat| cat.get_active_chars())
+            .into_iter()
+            .find(|cat| cat.get_name().eq_ignore_ascii_case(name))
+    }
+
+    // A code point shared by several categories must only appear once here, otherwise it would
+    // be picked more often than the others when filling the remaining length.
+    fn get_active_chars_for_active_categories(&self) -> Vec<char> {
+        self.get_character_pools()
+            .into_iter()
+            .flat_map(|pool| pool.chars)
             .collect()
     }
 
+    fn generate_unlimited_chars(
+        &self,
+        pools: &[&CharacterPool],
+        num_chars: usize,
+        rng: &mut ChaCha20Rng,
+    ) -> Vec<char> {
+        // Picking a pool in proportion to its size and then one of its characters is a uniform
+        // draw over all of them, without copying the pools into one.
+        let weight = |pool: &CharacterPool| match self.sampling_mode {
+            SamplingMode::Uniform => pool.chars.len() as u64,
+            SamplingMode::Weighted => pool.weight,
+        };
+        generate_weighted_random_chars_from_char_vecs(
+            pools
+                .iter()
+                .map(|pool| (pool.chars.as_slice(), weight(pool)))
+                .collect(),
+            num_chars,
+            rng,
+        )
+    }
+
+    // Picks the characters beyond each pool's minimum. Without any maximum this is a plain
+    // uniform or weighted draw; otherwise the number of characters per capped pool is sampled
+    // first, conditioned on the caps, and the unlimited pools share whatever is left.
+    fn generate_extra_chars(
+        &self,
+        pools: &[CharacterPool],
+        num_chars: usize,
+        rng: &mut ChaCha20Rng,
+    ) -> Vec<char> {
+        let (capped, unlimited): (Vec<&CharacterPool>, Vec<&CharacterPool>) = pools
+            .iter()
+            .filter(|pool| self.pool_takes_extra_chars(pool))
+            .partition(|pool| pool.max.is_some());
+        if capped.is_empty() {
+            return self.generate_unlimited_chars(&unlimited, num_chars, rng);
+        }
+
+        let pool_mass = |pool: &CharacterPool| match self.sampling_mode {
+            SamplingMode::Uniform => pool.chars.len() as f64,
+            SamplingMode::Weighted => pool.weight as f64,
+        };
+        let mut log_weights: Vec<f64> = capped.iter().map(|pool| pool_mass(pool).ln()).collect();
+        let mut caps: Vec<usize> = capped
+            .iter()
+            .map(|pool| pool.get_extra_cap().unwrap_or(0))
+            .collect();
+        if !unlimited.is_empty() {
+            log_weights.push(
+                unlimited
+                    .iter()
+                    .map(|pool| pool_mass(pool))
+                    .sum::<f64>()
+                    .ln(),
+            );
+            caps.push(num_chars);
+        }
+
+        let counts = sample_capped_multinomial(&log_weights, &caps, num_chars, rng).unwrap();
+        let mut result: Vec<char> = capped
+            .iter()
+            .zip(&counts)
+            .flat_map(|(pool, count)| generate_random_chars_from_char_vec(&pool.chars, *count, rng))
+            .collect();
+        if let Some(count) = counts.get(capped.len()).filter(|count| **count > 0) {
+            result.append(&mut self.generate_unlimited_chars(&unlimited, *count, rng));
+        }
+        result
+    }
+
+    // Candidates that break a rejection rule are thrown away and drawn again, which keeps the
+    // accepted passwords in the same proportions to each other as the candidates.
     pub fn generate_password(&self) -> String {
         let mut rng = generate_rng();
-        let mut result: Vec<char> = self
-            .get_active_categories()
-            .iter()
-            .filter_map(|cat| {
-                (cat.get_active_code_points().len() > 0).then(|| {
-                    generate_random_chars_from_char_vec(
-                        cat.get_active_chars(),
-                        cat.get_min_required_chars(),
-                        &mut rng,
-                    )
+        let mut sample = self.get_candidate_sampler();
+        loop {
+            let candidate = sample(&mut rng);
+            if self.passes_rules(&candidate) {
+                return candidate;
+            }
+        }
+    }
+
+    // Prepares whatever the generation mode needs once, so that drawing many candidates does not
+    // rebuild the character pools each time.
+    pub fn get_candidate_sampler(&self) -> Box<dyn FnMut(&mut ChaCha20Rng) -> String + '_> {
+        match self.generation_mode {
+            GenerationMode::Characters => {
+                self.get_character_sampler_for_length(self.get_password_length())
+            }
+            GenerationMode::Passphrase => {
+                let words = self.passphrase.wordlist.get_words();
+                Box::new(move |rng| self.generate_passphrase(&words, rng))
+            }
+            GenerationMode::Pronounceable => {
+                Box::new(|rng| self.generate_pronounceable_password(rng))
+            }
+            GenerationMode::Template => {
+                let parts = self.resolve_template().unwrap_or_default();
+                Box::new(move |rng| generate_from_template(&parts, rng))
+            }
+            GenerationMode::Regex => {
+                let automaton = self.get_regex_automaton().ok();
+                let length = self.get_regex_length();
+                Box::new(move |rng| {
+                    automaton
+                        .as_ref()
+                        .and_then(|automaton| automaton.generate(length, rng))
+                        .unwrap_or_default()
                 })
-            })
-            .flatten()
+            }
+        }
+    }
+
+    // Draws character passwords of exactly `length` units of the length unit.
+    pub fn get_character_sampler_for_length(
+        &self,
+        length: usize,
+    ) -> Box<dyn FnMut(&mut ChaCha20Rng) -> String + '_> {
+        match self.length_unit.counts_code_points() {
+            true => self.get_character_sampler(length),
+            false => self.get_length_unit_sampler(length),
+        }
+    }
+
+    // Draws character passwords of exactly `length` code points.
+    pub fn get_character_sampler(
+        &self,
+        length: usize,
+    ) -> Box<dyn FnMut(&mut ChaCha20Rng) -> String + '_> {
+        let pool_sets = self.get_restricted_pool_sets();
+        let index = WeightedIndex::new(self.get_restricted_pool_set_probabilities(length)).ok();
+        if self.places_ends() {
+            let mut samplers: Vec<_> = pool_sets
+                .into_iter()
+                .map(|pools| self.get_placed_sampler(pools, length))
+                .collect();
+            return Box::new(move |rng| match &index {
+                Some(index) => samplers[index.sample(rng)](rng),
+                None => String::new(),
+            });
+        }
+        Box::new(move |rng| match &index {
+            Some(index) => {
+                self.generate_character_password(&pool_sets[index.sample(rng)], length, rng)
+            }
+            None => String::new(),
+        })
+    }
+
+    fn generate_character_password(
+        &self,
+        pools: &[CharacterPool],
+        length: usize,
+        rng: &mut ChaCha20Rng,
+    ) -> String {
+        let mut result: Vec<char> = pools
+            .iter()
+            .flat_map(|pool| generate_random_chars_from_char_vec(&pool.chars, pool.min, rng))
             .collect();
-        let remaining_length = self.password_length - result.len();
+        let remaining_length = length - result.len();
         if remaining_length > 0 {
-            result.append(&mut generate_random_chars_from_char_vec(
-                self.get_active_chars_for_active_categories(),
-                remaining_length,
-                &mut rng,
-            ));
+            result.append(&mut self.generate_extra_chars(pools, remaining_length, rng));
         }
-        result.shuffle(&mut rng);
+        result.shuffle(rng);
         result.into_iter().collect::<String>()
     }
 
     pub fn get_password_length(&self) -> usize {
-        self.password_length
+        self.target_entropy
+            .and_then(|bits| self.get_length_for_entropy(bits))
+            .unwrap_or(self.password_length)
     }
 
     pub fn set_password_length(&mut self, length: usize) {
         self.password_length = length;
+        self.target_entropy = None;
+    }
+
+    pub fn get_generation_mode(&self) -> GenerationMode {
+        self.generation_mode
+    }
+
+    pub fn set_generation_mode(&mut self, mode: GenerationMode) {
+        self.generation_mode = mode;
+    }
+
+    pub fn get_sampling_mode(&self) -> SamplingMode {
+        self.sampling_mode
+    }
+
+    pub fn set_sampling_mode(&mut self, mode: SamplingMode) {
+        self.sampling_mode = mode;
+    }
+
+    pub fn has_positive_weight_for_active_categories(&self) -> bool {
+        self.get_active_categories()
+            .iter()
+            .any(|cat| cat.get_weight() > 0)
+    }
+
+    pub fn get_target_entropy(&self) -> Option<f64> {
+        self.target_entropy
+    }
+
+    pub fn set_target_entropy(&mut self, bits: Option<f64>) {
+        self.target_entropy = bits;
     }
 
     pub fn save_to_clipboard(&mut self, password: String) {
-        self.clipboard.set_text(password).unwrap();
+        self.clipboard
+            .get_or_insert_with(|| Clipboard::new().unwrap())
+            .set_text(password)
+            .unwrap();
+    }
+
+    pub fn has_active_categories(&self) -> bool {
+        !self.get_active_categories().is_empty()
     }
 
     pub fn get_min_required_chars_for_active_categories(&self) -> usize {
@@ -89,3 +437,224 @@ impl Config {
             .sum()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use seshat::unicode::props::Blk;
+
+    use super::{Config, SamplingMode};
+    use crate::app::config::{
+        category::CodePointCategory,
+        code_point::{FilterCondition, FilterValue},
+        policy::PolicyError,
+    };
+
+    const DIGITS: &str = "0123456789";
+
+    // Only "ASCII Digits" plus a second category sharing half of its code points, both without
+    // minimums so every character comes from the fill step.
+    fn overlapping_digits_config() -> Config {
+        let mut config = Config::new();
+        for cat in config.categories.iter_mut() {
+            cat.set_enabled(false);
+        }
+
+        let digits = config
+            .get_categories_by_name_mut("ASCII Digits")
+            .next()
+            .unwrap();
+        digits.set_enabled(true);
+        digits.set_min_required_chars(0);
+        let shared = digits
+            .get_code_points()
+            .into_iter()
+            .filter(|cpc| "01234".contains(cpc.get_char()))
+            .collect();
+
+        let mut overlap = CodePointCategory::new(
+            "Overlapping Digits",
+            true,
+            Box::new(FilterCondition::new(
+                "blk",
+                FilterValue::PropBlk(&[Blk::Ascii]),
+                true,
+            )),
+            0,
+        );
+        overlap.code_points = shared;
+        config.categories.push(overlap);
+        config
+    }
+
+    // "ASCII Lowercase Letters" together with a category of every ASCII letter, both without
+    // minimums.
+    fn overlapping_letters_config() -> Config {
+        let mut config = Config::new();
+        for cat in config.categories.iter_mut() {
+            cat.set_enabled(false);
+        }
+
+        let mut code_points = config
+            .get_categories_by_name_mut("ASCII Uppercase Letters")
+            .next()
+            .unwrap()
+            .get_code_points();
+        let lowercase = config
+            .get_categories_by_name_mut("ASCII Lowercase Letters")
+            .next()
+            .unwrap();
+        lowercase.set_enabled(true);
+        lowercase.set_min_required_chars(0);
+        code_points.extend(lowercase.get_code_points());
+
+        let mut letters = CodePointCategory::new(
+            "ASCII Letters",
+            true,
+            Box::new(FilterCondition::new(
+                "blk",
+                FilterValue::PropBlk(&[Blk::Ascii]),
+                true,
+            )),
+            0,
+        );
+        letters.code_points = code_points;
+        config.categories.push(letters);
+        config
+    }
+
+    fn overlapping_limits_error() -> PolicyError {
+        PolicyError::OverlappingLimits {
+            first: "ASCII Lowercase Letters".to_string(),
+            second: "ASCII Letters".to_string(),
+        }
+    }
+
+    #[test]
+    fn category_names_are_unique() {
+        let config = Config::new();
+        let names: HashSet<&str> = config
+            .get_all_categories()
+            .iter()
+            .map(|cat| cat.get_name())
+            .collect();
+        assert_eq!(names.len(), config.get_all_categories().len());
+    }
+
+    #[test]
+    fn fill_pool_is_the_set_union_of_enabled_code_points() {
+        let config = overlapping_digits_config();
+        let pool = config.get_active_chars_for_active_categories();
+        let unique: HashSet<char> = pool.iter().copied().collect();
+        assert_eq!(pool.len(), unique.len());
+        assert_eq!(unique, DIGITS.chars().collect());
+    }
+
+    #[test]
+    fn category_maximums_are_never_exceeded() {
+        let mut config = Config::new();
+        for cat in config.categories.iter_mut() {
+            cat.set_enabled(false);
+        }
+        let limits = [
+            ("ASCII Digits", 0, Some(3)),
+            ("ASCII Lowercase Letters", 2, Some(5)),
+            ("ASCII Uppercase Letters", 1, None),
+        ];
+        for (name, min, max) in limits {
+            let cat = config.get_categories_by_name_mut(name).next().unwrap();
+            cat.set_enabled(true);
+            cat.set_min_required_chars(min);
+            cat.set_max_allowed_chars(max);
+        }
+        config.set_password_length(12);
+
+        for mode in [SamplingMode::Uniform, SamplingMode::Weighted] {
+            config.set_sampling_mode(mode);
+            for _ in 0..500 {
+                let password = config.generate_password();
+                assert_eq!(password.chars().count(), 12);
+                let count = |f: fn(&char) -> bool| password.chars().filter(f).count();
+                assert!(count(char::is_ascii_digit) <= 3, "{}", password);
+                assert!(
+                    (2..=5).contains(&count(char::is_ascii_lowercase)),
+                    "{}",
+                    password
+                );
+                assert!(count(char::is_ascii_uppercase) >= 1, "{}", password);
+            }
+        }
+    }
+
+    #[test]
+    fn overlapping_categories_cannot_have_minimums() {
+        let mut config = overlapping_letters_config();
+        assert!(config.validate_policy().is_ok());
+
+        // Uppercase letters from "ASCII Letters" would otherwise meet the lowercase minimum.
+        for (name, min) in [("ASCII Lowercase Letters", 2), ("ASCII Letters", 1)] {
+            let cat = config.get_categories_by_name_mut(name).next().unwrap();
+            cat.set_min_required_chars(min);
+            assert!(
+                config
+                    .validate_policy()
+                    .unwrap_err()
+                    .contains(&overlapping_limits_error())
+            );
+            let cat = config.get_categories_by_name_mut(name).next().unwrap();
+            cat.set_min_required_chars(0);
+        }
+    }
+
+    #[test]
+    fn overlapping_categories_cannot_have_maximums() {
+        let mut config = overlapping_letters_config();
+        config.set_password_length(12);
+
+        // Lowercase letters drawn for "ASCII Letters" would otherwise go past the lowercase cap,
+        // and the other way around.
+        for (name, max) in [("ASCII Lowercase Letters", 3), ("ASCII Letters", 5)] {
+            let cat = config.get_categories_by_name_mut(name).next().unwrap();
+            cat.set_max_allowed_chars(Some(max));
+            assert!(
+                config
+                    .validate_policy()
+                    .unwrap_err()
+                    .contains(&overlapping_limits_error())
+            );
+            let cat = config.get_categories_by_name_mut(name).next().unwrap();
+            cat.set_max_allowed_chars(None);
+        }
+        assert!(config.validate_policy().is_ok());
+    }
+
+    #[test]
+    fn each_enabled_code_point_is_equally_likely() {
+        let mut config = overlapping_digits_config();
+        config.set_password_length(20_000);
+
+        let mut counts: HashMap<char, usize> = HashMap::new();
+        let rounds = 10;
+        for _ in 0..rounds {
+            for c in config.generate_password().chars() {
+                *counts.entry(c).or_insert(0) += 1;
+            }
+        }
+
+        // 200,000 draws over 10 code points: each count has a standard deviation of about 134,
+        // so 5% of the expected count is more than 7 standard deviations away.
+        let expected = (rounds * config.get_password_length()) as f64 / DIGITS.len() as f64;
+        assert_eq!(counts.len(), DIGITS.len());
+        for c in DIGITS.chars() {
+            let count = counts[&c] as f64;
+            assert!(
+                (count - expected).abs() < expected * 0.05,
+                "{:?} drawn {} times, expected about {}",
+                c,
+                count,
+                expected
+            );
+        }
+    }
+}
@@ -1,15 +1,65 @@
 pub mod data;
 
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
 use crate::app::config::code_point::{CodePointConfig, Filterable};
 
 pub use self::data::generate_code_point_categories;
 
+pub const DEFAULT_WEIGHT: u32 = 1;
+
+// Whether the characters of a category may, may not or must appear at the first or last position
+// of a password. When several categories are required at a position, any of them will do.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PositionRule {
+    #[default]
+    Allowed,
+    Forbidden,
+    Required,
+}
+
+impl PositionRule {
+    pub fn get_label(&self) -> &'static str {
+        match self {
+            PositionRule::Allowed => "Allowed",
+            PositionRule::Forbidden => "Not Allowed",
+            PositionRule::Required => "Required",
+        }
+    }
+}
+
+impl FromStr for PositionRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "allowed" => Ok(PositionRule::Allowed),
+            "forbidden" | "not-allowed" => Ok(PositionRule::Forbidden),
+            "required" => Ok(PositionRule::Required),
+            _ => Err(format!(
+                "unknown position rule \"{}\" (expected allowed, forbidden or required)",
+                s
+            )),
+        }
+    }
+}
+
 #[allow(dead_code)]
 pub struct CodePointCategory {
     name: String,
     enabled: bool,
     filters: Box<dyn Filterable>,
     min_characters: usize,
+    max_characters: Option<usize>,
+    weight: u32,
+    first: PositionRule,
+    last: PositionRule,
+    default_enabled: bool,
+    default_min_characters: usize,
+    default_first: PositionRule,
+    default_last: PositionRule,
     pub code_points: Vec<CodePointConfig>,
 }
 
@@ -25,10 +75,22 @@ impl CodePointCategory {
             enabled,
             filters,
             min_characters,
+            max_characters: None,
+            weight: DEFAULT_WEIGHT,
+            first: PositionRule::Allowed,
+            last: PositionRule::Allowed,
+            default_enabled: enabled,
+            default_min_characters: min_characters,
+            default_first: PositionRule::Allowed,
+            default_last: PositionRule::Allowed,
             code_points: Vec::new(),
         }
     }
 
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
     pub fn get_min_required_chars(&self) -> usize {
         self.min_characters
     }
@@ -37,6 +99,45 @@ impl CodePointCategory {
         self.min_characters = min_chars;
     }
 
+    pub fn get_max_allowed_chars(&self) -> Option<usize> {
+        self.max_characters
+    }
+
+    pub fn set_max_allowed_chars(&mut self, max_chars: Option<usize>) {
+        self.max_characters = max_chars;
+    }
+
+    pub fn get_weight(&self) -> u32 {
+        self.weight
+    }
+
+    pub fn set_weight(&mut self, weight: u32) {
+        self.weight = weight;
+    }
+
+    pub fn get_first_rule(&self) -> PositionRule {
+        self.first
+    }
+
+    pub fn set_first_rule(&mut self, rule: PositionRule) {
+        self.first = rule;
+    }
+
+    pub fn get_last_rule(&self) -> PositionRule {
+        self.last
+    }
+
+    pub fn set_last_rule(&mut self, rule: PositionRule) {
+        self.last = rule;
+    }
+
+    pub fn set_default_position_rules(&mut self, first: PositionRule, last: PositionRule) {
+        self.first = first;
+        self.last = last;
+        self.default_first = first;
+        self.default_last = last;
+    }
+
     pub fn get_code_points(&self) -> Vec<CodePointConfig> {
         self.code_points.clone()
     }
@@ -44,7 +145,7 @@ impl CodePointCategory {
     pub fn get_active_code_points(&self) -> Vec<CodePointConfig> {
         self.code_points
             .iter()
-            .filter(|cpc| cpc.is_enabled())
+            .filter(|cpc| cpc.is_active())
             .cloned()
             .collect()
     }
@@ -64,6 +165,13 @@ impl CodePointCategory {
             .collect()
     }
 
+    // Code points are kept in ascending order, so this is a binary search.
+    pub fn has_active_char(&self, c: char) -> bool {
+        self.code_points
+            .binary_search_by_key(&(c as u32), |cpc| cpc.get_u32())
+            .is_ok_and(|i| self.code_points[i].is_active())
+    }
+
     pub fn is_enabled(&self) -> bool {
         self.enabled
     }
@@ -75,14 +183,25 @@ impl CodePointCategory {
         }
     }
 
+    pub fn get_pretty_max_characters(&self) -> String {
+        match self.max_characters {
+            Some(max) => max.to_string(),
+            None => "Unlimited".to_string(),
+        }
+    }
+
     pub fn get_label(&self) -> String {
         format!(
-            "{} - {} - {}/{} Enabled Code Points - Minimum Characters Required: {}",
+            "{} - {} - {}/{} Enabled Code Points - Minimum Characters Required: {} - Maximum Characters Allowed: {} - Weight: {} - First: {} - Last: {}",
             self.name,
             self.get_pretty_enabled(),
             self.get_active_code_point_count(),
             self.get_code_point_count(),
-            self.min_characters
+            self.min_characters,
+            self.get_pretty_max_characters(),
+            self.weight,
+            self.first.get_label(),
+            self.last.get_label()
         )
     }
 
@@ -100,6 +219,22 @@ impl CodePointCategory {
         }
     }
 
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn reset_to_defaults(&mut self) {
+        self.enabled = self.default_enabled;
+        self.min_characters = self.default_min_characters;
+        self.max_characters = None;
+        self.weight = DEFAULT_WEIGHT;
+        self.first = self.default_first;
+        self.last = self.default_last;
+        for cpc in self.code_points.iter_mut() {
+            cpc.set_enabled(true);
+        }
+    }
+
     pub fn toggle_enabled(&mut self) {
         self.enabled = !self.enabled;
     }
@@ -1,7 +1,7 @@
 use seshat::unicode::props::{Blk, Gc, Sc};
 
 use crate::app::config::{
-    category::CodePointCategory,
+    category::{CodePointCategory, PositionRule},
     code_point::{
         FilterCondition, FilterConditionOp, FilterValue, Filterable, generate_all_code_points,
     },
@@ -633,6 +633,13 @@ fn west_asian_and_middle_eastern_characters() -> FilterCondition {
     )
 }
 
+// Forms often trim leading and trailing whitespace, which would silently change the password, so
+// whitespace categories default to staying off both ends.
+fn untrimmed(mut category: CodePointCategory) -> CodePointCategory {
+    category.set_default_position_rules(PositionRule::Forbidden, PositionRule::Forbidden);
+    category
+}
+
 pub fn generate_code_point_categories() -> Vec<CodePointCategory> {
     let all_code_points = generate_all_code_points();
 
@@ -662,7 +669,12 @@ pub fn generate_code_point_categories() -> Vec<CodePointCategory> {
             Box::new(ascii_extended_symbols()),
             1,
         ),
-        CodePointCategory::new("ASCII Space", true, Box::new(ascii_space()), 1),
+        untrimmed(CodePointCategory::new(
+            "ASCII Space",
+            true,
+            Box::new(ascii_space()),
+            1,
+        )),
         CodePointCategory::new(
             "African Characters",
             true,
@@ -676,12 +688,6 @@ pub fn generate_code_point_categories() -> Vec<CodePointCategory> {
             Box::new(european_characters()),
             1,
         ),
-        CodePointCategory::new(
-            "African Characters",
-            true,
-            Box::new(african_characters()),
-            1,
-        ),
         CodePointCategory::new("Extended Numbers", true, Box::new(extended_numbers()), 1),
         CodePointCategory::new(
             "Extended Symbols and Emojis",
@@ -727,7 +733,12 @@ pub fn generate_code_point_categories() -> Vec<CodePointCategory> {
             Box::new(linear_a_linear_b_characters()),
             1,
         ),
-        CodePointCategory::new("Non-ASCII Space", true, Box::new(non_ascii_space()), 1),
+        untrimmed(CodePointCategory::new(
+            "Non-ASCII Space",
+            true,
+            Box::new(non_ascii_space()),
+            1,
+        )),
         CodePointCategory::new(
             "Non-CJK Central and East Asian Characters",
             true,
@@ -8,6 +8,8 @@ use std::any::Any;
 #[derive(Clone, Copy)]
 pub struct CodePointConfig {
     enabled: bool,
+    // Set by the character filters of the whole configuration, independently of `enabled`.
+    excluded: bool,
     code_point: CodePoint,
 }
 
@@ -35,7 +37,7 @@ pub trait Filterable: Any {
     fn get_type(&self) -> &str;
 }
 
-#[derive(Copy)]
+#[derive(Clone, Copy)]
 pub struct FilterCondition {
     func: &'static str,
     values: FilterValue,
@@ -58,16 +60,6 @@ impl Filterable for FilterCondition {
     }
 }
 
-impl Clone for FilterCondition {
-    fn clone(&self) -> Self {
-        FilterCondition {
-            func: self.func,
-            values: self.values.clone(),
-            include: self.include,
-        }
-    }
-}
-
 pub enum FilterConditionOp {
     And(&'static [Box<dyn Filterable>]),
     Or(&'static [Box<dyn Filterable>]),
@@ -85,6 +77,10 @@ impl Filterable for FilterConditionOp {
 }
 
 impl CodePointConfig {
+    pub fn get_u32(&self) -> u32 {
+        self.code_point.to_u32()
+    }
+
     pub fn get_char(&self) -> char {
         char::from_u32(self.code_point.to_u32()).unwrap_or(char::REPLACEMENT_CHARACTER)
     }
@@ -201,10 +197,19 @@ impl CodePointConfig {
         self.enabled
     }
 
+    pub fn set_excluded(&mut self, excluded: bool) {
+        self.excluded = excluded;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.enabled && !self.excluded
+    }
+
     fn get_pretty_enabled(&self) -> String {
-        match self.enabled {
-            true => "Enabled".to_string(),
-            false => "Disabled".to_string(),
+        match (self.enabled, self.excluded) {
+            (true, false) => "Enabled".to_string(),
+            (true, true) => "Excluded by Filters".to_string(),
+            (false, _) => "Disabled".to_string(),
         }
     }
 
@@ -737,12 +742,13 @@ pub fn generate_all_code_points() -> Vec<CodePointConfig> {
         .filter_map(|cp| {
             let cpc = CodePointConfig {
                 enabled: true,
+                excluded: false,
                 code_point: cp,
             };
             filters_vec
                 .iter()
                 .all(|f| cpc.matches_filter_condition_op(f))
-                .then(|| cpc)
+                .then_some(cpc)
         })
         .collect()
 }
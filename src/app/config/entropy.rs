@@ -0,0 +1,534 @@
+use std::ops::RangeInclusive;
+
+use crate::app::config::{
+    Config, GenerationMode, SamplingMode, pool::CharacterPool, script::RestrictionLevel,
+};
+
+const MAX_DERIVED_LENGTH: usize = 1 << 16;
+
+#[derive(Clone, Copy)]
+pub struct EntropyPool {
+    pub size: usize,
+    pub min: usize,
+    pub max: Option<usize>,
+    pub weight: u64,
+    // How many of the characters may start the password, end it, or make up all of it.
+    pub first: usize,
+    pub last: usize,
+    pub both: usize,
+}
+
+pub fn log2_add(a: f64, b: f64) -> f64 {
+    match (a, b) {
+        (f64::NEG_INFINITY, _) => b,
+        (_, f64::NEG_INFINITY) => a,
+        _ => {
+            let (hi, lo) = if a > b { (a, b) } else { (b, a) };
+            hi + (lo - hi).exp2().ln_1p() / std::f64::consts::LN_2
+        }
+    }
+}
+
+pub fn log2_factorials(n: usize) -> Vec<f64> {
+    let mut result = vec![0.0; n + 1];
+    for k in 1..=n {
+        result[k] = result[k - 1] + (k as f64).log2();
+    }
+    result
+}
+
+// log2 of the convolution of two log2-domain coefficient lists, truncated to `len` entries.
+fn log2_convolve(a: &[f64], b: &[f64], len: usize) -> Vec<f64> {
+    let mut result = vec![f64::NEG_INFINITY; len];
+    for (i, x) in a.iter().enumerate().filter(|(_, x)| x.is_finite()) {
+        for (j, y) in b.iter().enumerate().take(len - i) {
+            result[i + j] = log2_add(result[i + j], x + y);
+        }
+    }
+    result
+}
+
+// Shannon entropy, in bits, of `generate_password`: each pool's minimum is drawn uniformly from
+// the pool, the remaining characters pick a pool by weight and then a character uniformly within
+// it, and the result is shuffled. Uniform mode is the case where every weight is the pool size.
+//
+// Given the number of characters `k_i` taken from each pool, every arrangement is equally likely,
+// so H = H(K) + E[log2 N(K)] with N(k) = length! / prod k_i! * prod size_i^k_i. The extra draws
+// per pool follow a multinomial distribution, and both terms split into sums over the binomial
+// marginals, which keeps the computation linear in the length for each pool.
+pub fn weighted_entropy_bits(pools: &[EntropyPool], length: usize) -> f64 {
+    if pools.iter().any(|pool| pool.max.is_some()) {
+        return capped_weighted_entropy_bits(pools, length)[length];
+    }
+    let pools: Vec<&EntropyPool> = pools.iter().filter(|pool| pool.size > 0).collect();
+    let total_min: usize = pools.iter().map(|pool| pool.min).sum();
+    if pools.is_empty() || length < total_min {
+        return 0.0;
+    }
+    let remaining = length - total_min;
+    let total_weight: u64 = pools.iter().map(|pool| pool.weight).sum();
+    if remaining > 0 && total_weight == 0 {
+        return 0.0;
+    }
+
+    let log_fact = log2_factorials(length);
+    let mut bits = log_fact[length] - log_fact[remaining];
+    for pool in pools {
+        let log_size = (pool.size as f64).log2();
+        bits += pool.min as f64 * log_size;
+        if remaining == 0 || pool.weight == 0 {
+            bits -= log_fact[pool.min];
+            continue;
+        }
+
+        let p = pool.weight as f64 / total_weight as f64;
+        let log_p = p.log2();
+        let log_q = (1.0 - p).log2();
+        let term = |j: usize| log_fact[j] - log_fact[pool.min + j] + j as f64 * (log_size - log_p);
+        if p >= 1.0 {
+            bits += term(remaining);
+            continue;
+        }
+        for j in 0..=remaining {
+            let log_pmf = log_fact[remaining] - log_fact[j] - log_fact[remaining - j]
+                + j as f64 * log_p
+                + (remaining - j) as f64 * log_q;
+            bits += log_pmf.exp2() * term(j);
+        }
+    }
+    bits
+}
+
+// Same as `weighted_entropy_bits`, but with the extra draws conditioned on the pool maximums, for
+// every length up to `longest` at once. The marginals are no longer binomial, so they come from
+// convolutions of each pool's generating function with those of the others, and the log of the
+// probability of staying within the caps is added back. Pools whose cap never binds are handled
+// as one group: the number of draws U the group takes follows from the convolution with the
+// capped pools, and given U each pool of the group takes a binomial share of them. Each capped
+// pool then costs one convolution, and each pool of the group one pass over the values of U.
+fn capped_weighted_entropy_bits(pools: &[EntropyPool], longest: usize) -> Vec<f64> {
+    let mut result = vec![0.0; longest + 1];
+    let pools: Vec<&EntropyPool> = pools.iter().filter(|pool| pool.size > 0).collect();
+    let total_min: usize = pools.iter().map(|pool| pool.min).sum();
+    if pools.is_empty() || longest < total_min {
+        return result;
+    }
+    let most = longest - total_min;
+    let total_weight: u64 = pools.iter().map(|pool| pool.weight).sum();
+    let log_fact = log2_factorials(longest);
+    let log_p = |weight: u64| (weight as f64 / total_weight.max(1) as f64).log2();
+    let cap = |pool: &EntropyPool| match pool.weight {
+        0 => 0,
+        _ => pool
+            .max
+            .map_or(most, |max| max.saturating_sub(pool.min))
+            .min(most),
+    };
+    // log2(p^j / j!) for every number of extra draws j up to `cap`.
+    let terms = |log_p: f64, cap: usize| -> Vec<f64> {
+        (0..=cap)
+            .map(|j| match j {
+                0 => 0.0,
+                _ => j as f64 * log_p - log_fact[j],
+            })
+            .collect()
+    };
+    // What a pool adds to the entropy when it supplies j extra characters.
+    let gain = |pool: &EntropyPool, j: usize| {
+        let extra = match j {
+            0 => 0.0,
+            _ => j as f64 * ((pool.size as f64).log2() - log_p(pool.weight)),
+        };
+        log_fact[j] - log_fact[pool.min + j] + extra
+    };
+
+    let (capped, grouped): (Vec<&EntropyPool>, Vec<&EntropyPool>) = pools
+        .iter()
+        .partition(|pool| pool.weight == 0 || cap(pool) < most);
+    let group_weight: u64 = grouped.iter().map(|pool| pool.weight).sum();
+    let group = match grouped.is_empty() {
+        true => vec![0.0],
+        false => terms(log_p(group_weight), most),
+    };
+    let capped_terms: Vec<Vec<f64>> = capped
+        .iter()
+        .map(|pool| terms(log_p(pool.weight), cap(pool)))
+        .collect();
+
+    let len = most + 1;
+    let mut unit = vec![f64::NEG_INFINITY; len];
+    unit[0] = 0.0;
+    let mut prefix = vec![unit.clone()];
+    for term in &capped_terms {
+        let next = log2_convolve(prefix.last().unwrap(), term, len);
+        prefix.push(next);
+    }
+    let mut suffix = vec![unit; capped_terms.len() + 1];
+    for i in (0..capped_terms.len()).rev() {
+        suffix[i] = log2_convolve(&capped_terms[i], &suffix[i + 1], len);
+    }
+    let all_capped = &prefix[capped_terms.len()];
+    let log_z = log2_convolve(all_capped, &group, len);
+    let others: Vec<Vec<f64>> = (0..capped.len())
+        .map(|i| log2_convolve(&log2_convolve(&prefix[i], &suffix[i + 1], len), &group, len))
+        .collect();
+
+    // The expected gain of the group's pools given the number of draws u the group takes.
+    let mut group_gain = vec![0.0; len];
+    for pool in &grouped {
+        let q = pool.weight as f64 / group_weight as f64;
+        for (u, gain_u) in group_gain.iter_mut().enumerate() {
+            *gain_u += match q >= 1.0 {
+                true => gain(pool, u),
+                false => (0..=u)
+                    .map(|j| {
+                        let log_pmf = log_fact[u] - log_fact[j] - log_fact[u - j]
+                            + j as f64 * q.log2()
+                            + (u - j) as f64 * (1.0 - q).log2();
+                        log_pmf.exp2() * gain(pool, j)
+                    })
+                    .sum(),
+            };
+        }
+    }
+
+    let fixed: f64 = pools
+        .iter()
+        .map(|pool| pool.min as f64 * (pool.size as f64).log2())
+        .sum();
+    for r in (0..len).filter(|r| log_z[*r] > f64::NEG_INFINITY) {
+        let length = total_min + r;
+        let mut bits = log_fact[length] + log_z[r] + fixed;
+        for (i, pool) in capped.iter().enumerate() {
+            for (j, a) in capped_terms[i].iter().enumerate().take(r + 1) {
+                let probability = (a + others[i][r - j] - log_z[r]).exp2();
+                if probability > 0.0 {
+                    bits += probability * gain(pool, j);
+                }
+            }
+        }
+        for (u, a) in group.iter().enumerate().take(r + 1) {
+            let probability = (a + all_capped[r - u] - log_z[r]).exp2();
+            if probability > 0.0 {
+                bits += probability * group_gain[u];
+            }
+        }
+        result[length] = bits;
+    }
+    result
+}
+
+// `weighted_entropy_bits` for every length in `lengths`.
+pub fn weighted_entropy_bits_for_lengths(
+    pools: &[EntropyPool],
+    lengths: RangeInclusive<usize>,
+) -> Vec<f64> {
+    match pools.iter().any(|pool| pool.max.is_some()) {
+        true => capped_weighted_entropy_bits(pools, *lengths.end())[lengths].to_vec(),
+        false => lengths
+            .map(|length| weighted_entropy_bits(pools, length))
+            .collect(),
+    }
+}
+
+// Whether `bits` meet a target of `target` bits, allowing for the rounding of sums of logarithms.
+pub fn reaches_entropy(bits: f64, target: f64) -> bool {
+    bits >= target - 1e-9
+}
+
+pub fn format_entropy(bits: f64) -> String {
+    format!("{:.1} bits", bits)
+}
+
+impl Config {
+    pub fn get_entropy_pools(&self, pools: &[CharacterPool]) -> Vec<EntropyPool> {
+        let places_ends = self.places_ends();
+        pools
+            .iter()
+            .map(|pool| {
+                let (first, last, both) = match places_ends {
+                    true => {
+                        let ends = self.get_pool_ends(pool);
+                        (ends.first.len(), ends.last.len(), ends.both.len())
+                    }
+                    false => (pool.chars.len(), pool.chars.len(), pool.chars.len()),
+                };
+                EntropyPool {
+                    size: pool.chars.len(),
+                    min: pool.min,
+                    max: pool.max,
+                    weight: pool.weight,
+                    first,
+                    last,
+                    both,
+                }
+            })
+            .collect()
+    }
+
+    // One list of pools per script combination the restriction level allows.
+    pub fn get_entropy_pool_sets(&self) -> Vec<Vec<EntropyPool>> {
+        self.get_restricted_pool_sets()
+            .iter()
+            .map(|pools| self.get_entropy_pools(pools))
+            .collect()
+    }
+
+    // Whether a password of `length` characters fits within the minimums and maximums of `pools`.
+    pub fn fits_pools(&self, pools: &[EntropyPool], length: usize) -> bool {
+        let minimum: usize = pools.iter().map(|pool| pool.min).sum();
+        let maximum: Option<usize> = pools
+            .iter()
+            .map(|pool| match self.takes_extra_chars(pool.weight) {
+                true => pool.max,
+                false => Some(pool.min),
+            })
+            .sum();
+        minimum <= length && maximum.is_none_or(|maximum| maximum >= length)
+    }
+
+    // The entropy of each pool set at `length`, or `None` for the sets that cannot produce a
+    // password of that length.
+    pub fn get_entropy_bits_for_pool_sets(
+        &self,
+        pool_sets: &[Vec<EntropyPool>],
+        length: usize,
+    ) -> Vec<Option<f64>> {
+        pool_sets
+            .iter()
+            .map(|pools| match self.places_ends() {
+                true => self.get_placed_entropy_bits(pools, length),
+                false => self
+                    .fits_pools(pools, length)
+                    .then(|| self.get_drawn_entropy_bits(pools, length)),
+            })
+            .collect()
+    }
+
+    // The probability of drawing from each pool set. Picking a set in proportion to 2^entropy
+    // of its passwords gives the most entropy the choice of set can add.
+    pub fn get_pool_set_probabilities(&self, bits: &[Option<f64>]) -> Vec<f64> {
+        let max = bits
+            .iter()
+            .flatten()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f64> = bits
+            .iter()
+            .map(|bits| bits.map_or(0.0, |bits| (bits - max).exp2()))
+            .collect();
+        let total: f64 = weights.iter().sum();
+        weights
+            .into_iter()
+            .map(|weight| match total > 0.0 {
+                true => weight / total,
+                false => 0.0,
+            })
+            .collect()
+    }
+
+    // The probability of drawing from each set of `get_restricted_pool_sets`.
+    pub fn get_restricted_pool_set_probabilities(&self, length: usize) -> Vec<f64> {
+        if self.restriction_level == RestrictionLevel::Unrestricted {
+            return vec![1.0];
+        }
+        let bits = self.get_entropy_bits_for_pool_sets(&self.get_entropy_pool_sets(), length);
+        self.get_pool_set_probabilities(&bits)
+    }
+
+    // Passwords from different script combinations can coincide when they only use characters
+    // common to all scripts, so the expected entropy of the chosen set is a lower bound.
+    pub fn get_character_entropy_bits(&self, pool_sets: &[Vec<EntropyPool>], length: usize) -> f64 {
+        let bits = self.get_entropy_bits_for_pool_sets(pool_sets, length);
+        self.get_pool_set_probabilities(&bits)
+            .iter()
+            .zip(&bits)
+            .map(|(p, bits)| p * bits.unwrap_or(0.0))
+            .sum()
+    }
+
+    // The entropy of `generate_character_password`.
+    pub fn get_drawn_entropy_bits(&self, pools: &[EntropyPool], length: usize) -> f64 {
+        weighted_entropy_bits(&self.get_drawn_weights(pools), length)
+    }
+
+    // The same for every length in `lengths`.
+    pub fn get_drawn_entropy_bits_for_lengths(
+        &self,
+        pools: &[EntropyPool],
+        lengths: RangeInclusive<usize>,
+    ) -> Vec<f64> {
+        weighted_entropy_bits_for_lengths(&self.get_drawn_weights(pools), lengths)
+    }
+
+    // The minimums make some strings likelier than others in uniform mode too, so its entropy is
+    // that of weights equal to the pool sizes, less than the log2 of the number of valid strings.
+    fn get_drawn_weights(&self, pools: &[EntropyPool]) -> Vec<EntropyPool> {
+        pools
+            .iter()
+            .map(|pool| EntropyPool {
+                weight: match self.sampling_mode {
+                    SamplingMode::Uniform => pool.size as u64,
+                    SamplingMode::Weighted => pool.weight,
+                },
+                ..*pool
+            })
+            .collect()
+    }
+
+    // Rejecting candidates removes exactly log2 of the acceptance rate from the entropy when every
+    // candidate is equally likely. Otherwise the likelier candidates can be rejected more or less
+    // often than the rest, so this is an estimate.
+    pub fn get_entropy_bits(&self) -> f64 {
+        let acceptance_rate = self.get_acceptance_rate();
+        match acceptance_rate > 0.0 {
+            true => (self.get_candidate_entropy_bits() + acceptance_rate.log2()).max(0.0),
+            false => 0.0,
+        }
+    }
+
+    fn get_candidate_entropy_bits(&self) -> f64 {
+        match self.generation_mode {
+            GenerationMode::Characters => match self.length_unit.counts_code_points() {
+                true => self.get_character_entropy_bits(
+                    &self.get_entropy_pool_sets(),
+                    self.get_password_length(),
+                ),
+                false => self.get_length_unit_entropy_bits(self.get_password_length()),
+            },
+            GenerationMode::Passphrase => self.get_passphrase_entropy_bits(self.get_word_count()),
+            GenerationMode::Pronounceable => {
+                self.get_pronounceable_entropy_bits(self.get_syllable_count())
+            }
+            GenerationMode::Template => self.get_template_entropy_bits(),
+            GenerationMode::Regex => self.get_regex_entropy_bits(),
+        }
+    }
+
+    // Returns the shortest password length whose policy entropy reaches `bits`, or `None` when the
+    // enabled pool cannot reach it (e.g. a single enabled character).
+    pub fn get_length_for_entropy(&self, bits: f64) -> Option<usize> {
+        let pool_sets = self.get_entropy_pool_sets();
+        let pool_size = pool_sets
+            .iter()
+            .map(|pools| pools.iter().map(|pool| pool.size).sum::<usize>())
+            .max()
+            .unwrap_or(0);
+        if pool_size < 2 {
+            return None;
+        }
+        let min_length = pool_sets
+            .iter()
+            .map(|pools| pools.iter().map(|pool| pool.min).sum::<usize>())
+            .min()
+            .unwrap_or(0)
+            .max(1);
+        let unit = self.length_unit;
+        // Rejection takes its share of the entropy off every length, as in `get_entropy_bits`.
+        let reaches = |length: usize| {
+            let length_bits = match unit.counts_code_points() {
+                true => self.get_character_entropy_bits(&pool_sets, length),
+                false => self.get_length_unit_entropy_bits(length),
+            };
+            let acceptance_rate = self.get_acceptance_rate_for_length(length);
+            acceptance_rate > 0.0 && reaches_entropy(length_bits + acceptance_rate.log2(), bits)
+        };
+        let max_length = self
+            .get_max_total_chars_for_active_categories()
+            .map(|max| max * unit.get_max_units_per_char())
+            .unwrap_or(MAX_DERIVED_LENGTH)
+            .min(MAX_DERIVED_LENGTH);
+
+        // No character can carry more than log2(pool_size) bits, which bounds the search from
+        // below, since every character takes up at least one unit.
+        let mut lo = min_length.max((bits / (pool_size as f64).log2()).ceil() as usize);
+        if lo > max_length {
+            return None;
+        }
+        if reaches(lo) {
+            return Some(lo);
+        }
+        let mut hi = (lo * 2).min(max_length);
+        while !reaches(hi) {
+            if hi >= max_length {
+                return None;
+            }
+            lo = hi;
+            hi = (hi * 2).min(max_length);
+        }
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            match reaches(mid) {
+                true => hi = mid,
+                false => lo = mid,
+            }
+        }
+        Some(hi)
+    }
+
+    pub fn get_entropy_label(&self) -> String {
+        format_entropy(self.get_entropy_bits())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EntropyPool, weighted_entropy_bits};
+    use crate::app::config::Config;
+
+    fn digits_config() -> Config {
+        let mut config = Config::new();
+        for cat in config.categories.iter_mut() {
+            cat.set_enabled(false);
+        }
+        let digits = config
+            .get_categories_by_name_mut("ASCII Digits")
+            .next()
+            .unwrap();
+        digits.set_enabled(true);
+        digits.set_min_required_chars(0);
+        config
+    }
+
+    fn pool(size: usize, weight: u64) -> EntropyPool {
+        EntropyPool {
+            size,
+            min: 0,
+            max: None,
+            weight,
+            first: size,
+            last: size,
+            both: size,
+        }
+    }
+
+    #[test]
+    fn uniform_passwords_carry_log2_of_the_pool_per_character() {
+        let mut config = digits_config();
+        config.set_password_length(10);
+        assert!((config.get_entropy_bits() - 10.0 * 10f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn weights_spread_the_entropy_unevenly() {
+        // Drawing either pool with equal weight is uniform over all 20 characters.
+        let even = weighted_entropy_bits(&[pool(10, 1), pool(10, 1)], 4);
+        assert!((even - 4.0 * 20f64.log2()).abs() < 1e-9);
+        // Favouring one pool makes its characters likelier and the password more predictable.
+        let skewed = weighted_entropy_bits(&[pool(10, 3), pool(10, 1)], 4);
+        assert!(skewed < even);
+        assert!(skewed > 4.0 * 10f64.log2());
+    }
+
+    #[test]
+    fn derived_lengths_make_up_for_rejected_candidates() {
+        let mut config = digits_config();
+        config.set_target_entropy(Some(63.0));
+        let unrestricted = config.get_password_length();
+        assert!(config.get_entropy_bits() >= 63.0 - 1e-9);
+
+        config.get_rejection_rules_mut().max_consecutive = Some(1);
+        assert!(config.get_password_length() > unrestricted);
+        assert!(config.get_entropy_bits() >= 63.0 - 1e-9);
+    }
+}
@@ -0,0 +1,353 @@
+use std::{collections::HashSet, str::FromStr};
+
+use seshat::unicode::props::Age;
+
+use serde::{Deserialize, Serialize};
+
+use unicode_normalization::UnicodeNormalization;
+
+use unicode_security::skeleton;
+
+use crate::app::config::{
+    Config,
+    code_point::{FilterCondition, FilterValue, Filterable},
+};
+
+// Characters that are commonly mistaken for one another when read off a screen or paper.
+const ASCII_AMBIGUOUS: &str = "0Oo1lI|`'\"";
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConfusableExclusion {
+    #[default]
+    None,
+    // A short fixed list of lookalike ASCII characters.
+    AsciiAmbiguous,
+    // Every code point that UTS #39 considers confusable with a more common one, across all
+    // categories and scripts.
+    Uts39,
+}
+
+impl ConfusableExclusion {
+    pub fn get_label(&self) -> &'static str {
+        match self {
+            ConfusableExclusion::None => "None",
+            ConfusableExclusion::AsciiAmbiguous => "ASCII Ambiguous",
+            ConfusableExclusion::Uts39 => "UTS #39 Confusables",
+        }
+    }
+}
+
+impl FromStr for ConfusableExclusion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(ConfusableExclusion::None),
+            "ascii" | "ascii-ambiguous" => Ok(ConfusableExclusion::AsciiAmbiguous),
+            "uts39" => Ok(ConfusableExclusion::Uts39),
+            _ => Err(format!(
+                "unknown confusable exclusion \"{}\" (expected none, ascii or uts39)",
+                s
+            )),
+        }
+    }
+}
+
+// Every Unicode version that assigned characters, oldest first, and the ages of the code points
+// each one assigned.
+const UNICODE_VERSION_NAMES: [&str; 27] = [
+    "1.1", "2.0", "2.1", "3.0", "3.1", "3.2", "4.0", "4.1", "5.0", "5.1", "5.2", "6.0", "6.1",
+    "6.2", "6.3", "7.0", "8.0", "9.0", "10.0", "11.0", "12.0", "12.1", "13.0", "14.0", "15.0",
+    "15.1", "16.0",
+];
+static UNICODE_AGES: [Age; 27] = [
+    Age::V1_1,
+    Age::V2_0,
+    Age::V2_1,
+    Age::V3_0,
+    Age::V3_1,
+    Age::V3_2,
+    Age::V4_0,
+    Age::V4_1,
+    Age::V5_0,
+    Age::V5_1,
+    Age::V5_2,
+    Age::V6_0,
+    Age::V6_1,
+    Age::V6_2,
+    Age::V6_3,
+    Age::V7_0,
+    Age::V8_0,
+    Age::V9_0,
+    Age::V10_0,
+    Age::V11_0,
+    Age::V12_0,
+    Age::V12_1,
+    Age::V13_0,
+    Age::V14_0,
+    Age::V15_0,
+    Age::V15_1,
+    Age::V16_0,
+];
+
+// A Unicode version, stored as its position in `UNICODE_VERSION_NAMES` and saved as its name.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct UnicodeVersion(usize);
+
+impl UnicodeVersion {
+    pub fn all() -> impl Iterator<Item = UnicodeVersion> {
+        (0..UNICODE_VERSION_NAMES.len()).map(UnicodeVersion)
+    }
+
+    pub fn get_label(&self) -> &'static str {
+        UNICODE_VERSION_NAMES[self.0]
+    }
+
+    // The ages of the code points assigned after this version.
+    fn get_newer_ages(&self) -> &'static [Age] {
+        &UNICODE_AGES[self.0 + 1..]
+    }
+}
+
+impl FromStr for UnicodeVersion {
+    type Err = String;
+
+    // Accepts "6", "6.0" and "6.0.0" alike, since characters are only ever added in minor
+    // versions.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().split('.');
+        let major = parts.next().unwrap_or_default();
+        let minor = parts.next().unwrap_or("0");
+        let name = format!("{}.{}", major, minor);
+        UNICODE_VERSION_NAMES
+            .iter()
+            .position(|label| *label == name)
+            .filter(|_| parts.all(|part| part == "0"))
+            .map(UnicodeVersion)
+            .ok_or_else(|| {
+                format!(
+                    "unknown Unicode version \"{}\" (expected 1.1 to {})",
+                    s,
+                    UNICODE_VERSION_NAMES[UNICODE_VERSION_NAMES.len() - 1]
+                )
+            })
+    }
+}
+
+impl TryFrom<String> for UnicodeVersion {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<UnicodeVersion> for String {
+    fn from(version: UnicodeVersion) -> Self {
+        version.get_label().to_string()
+    }
+}
+
+// Whether lowercasing or case folding `c` gives back `c`, and uppercasing it does not merge it
+// with another character: this rules out uppercase and titlecase letters, but also ß, ſ, the
+// final sigma and the Turkish dotless i, whose uppercase lowercases to something else.
+fn is_case_stable(c: char) -> bool {
+    c.to_lowercase().eq([c]) && c.to_uppercase().flat_map(char::to_lowercase).eq([c])
+}
+
+impl Config {
+    pub fn is_case_fold_safe(&self) -> bool {
+        self.case_fold_safe
+    }
+
+    pub fn set_case_fold_safe(&mut self, safe: bool) {
+        self.case_fold_safe = safe;
+        self.update_exclusions();
+    }
+
+    // Passphrases and templates can capitalize characters the filters kept, so whole passwords
+    // are checked as well.
+    pub fn passes_case_folding(&self, password: &str) -> bool {
+        !self.case_fold_safe || password.chars().all(is_case_stable)
+    }
+
+    pub fn get_max_unicode_version(&self) -> Option<UnicodeVersion> {
+        self.max_unicode_version
+    }
+
+    pub fn set_max_unicode_version(&mut self, version: Option<UnicodeVersion>) {
+        self.max_unicode_version = version;
+        self.update_exclusions();
+    }
+
+    pub fn get_confusable_exclusion(&self) -> ConfusableExclusion {
+        self.confusables
+    }
+
+    pub fn set_confusable_exclusion(&mut self, exclusion: ConfusableExclusion) {
+        self.confusables = exclusion;
+        self.update_exclusions();
+    }
+
+    // UTS #39 maps every code point to a skeleton, and code points with the same skeleton are
+    // confusable. Only the code points that are their own skeleton, up to canonical
+    // decomposition, and their own NFC are kept: code points with the same skeleton would then
+    // be canonically equivalent and so the same code point. That leaves at most one code point
+    // per skeleton, so no two characters passgen can generate look alike, without having to
+    // look at which categories are enabled.
+    fn get_confusable_chars(&self) -> HashSet<char> {
+        self.categories
+            .iter()
+            .flat_map(|cat| cat.code_points.iter().map(|cpc| cpc.get_char()))
+            .filter(|c| {
+                let s = c.to_string();
+                !skeleton(&s).eq(s.nfd()) || !s.nfc().eq(s.chars())
+            })
+            .collect()
+    }
+
+    // Recomputes which code points the character filters exclude. Called whenever a filter
+    // setting changes.
+    pub fn update_exclusions(&mut self) {
+        let excluded: HashSet<char> = match self.confusables {
+            ConfusableExclusion::None => HashSet::new(),
+            ConfusableExclusion::AsciiAmbiguous => ASCII_AMBIGUOUS.chars().collect(),
+            ConfusableExclusion::Uts39 => self.get_confusable_chars(),
+        };
+        let age_filter: Option<Box<dyn Filterable>> = self.max_unicode_version.map(|version| {
+            Box::new(FilterCondition::new(
+                "age",
+                FilterValue::PropAge(version.get_newer_ages()),
+                false,
+            )) as Box<dyn Filterable>
+        });
+        // Only cased code points have case mappings at all.
+        let cased_filter: Option<Box<dyn Filterable>> = self.case_fold_safe.then(|| {
+            Box::new(FilterCondition::new(
+                "cased",
+                FilterValue::PropBinary(true),
+                true,
+            )) as Box<dyn Filterable>
+        });
+        let normalization = self.normalization;
+        for cat in self.categories.iter_mut() {
+            for cpc in cat.code_points.iter_mut() {
+                let c = cpc.get_char();
+                let too_new = age_filter
+                    .as_ref()
+                    .is_some_and(|filter| !cpc.matches_filter_condition_op(filter));
+                let case_unstable = cased_filter.as_ref().is_some_and(|filter| {
+                    cpc.matches_filter_condition_op(filter) && !is_case_stable(c)
+                });
+                cpc.set_excluded(
+                    too_new
+                        || case_unstable
+                        || !normalization.is_stable(c)
+                        || excluded.contains(&c),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use unicode_security::skeleton;
+
+    use super::{ConfusableExclusion, UnicodeVersion, is_case_stable};
+    use crate::app::config::Config;
+
+    fn is_active(config: &Config, c: char) -> bool {
+        config.categories.iter().any(|cat| cat.has_active_char(c))
+    }
+
+    #[test]
+    fn ascii_ambiguous_characters_are_excluded() {
+        let mut config = Config::new();
+        assert!(is_active(&config, '0'));
+        config.set_confusable_exclusion(ConfusableExclusion::AsciiAmbiguous);
+        for c in "0Oo1lI|`'\"".chars() {
+            assert!(!is_active(&config, c), "{:?}", c);
+        }
+        assert!(is_active(&config, 'a'));
+        config.set_confusable_exclusion(ConfusableExclusion::None);
+        assert!(is_active(&config, '0'));
+    }
+
+    #[test]
+    fn uts39_keeps_one_code_point_per_skeleton() {
+        let mut config = Config::new();
+        assert!(is_active(&config, '\u{430}'));
+        config.set_confusable_exclusion(ConfusableExclusion::Uts39);
+        // Cyrillic а, the digit zero and the digit one look like a, O and l.
+        assert!(!is_active(&config, '\u{430}'));
+        assert!(is_active(&config, 'a'));
+        assert!(!is_active(&config, '0'));
+        assert!(is_active(&config, 'O'));
+        assert!(!is_active(&config, '1'));
+        assert!(is_active(&config, 'l'));
+        // CJK compatibility ideographs decompose to the unified ideographs they duplicate.
+        assert!(!is_active(&config, '\u{F900}'));
+
+        let mut skeletons = HashSet::new();
+        for cat in &config.categories {
+            for c in cat.get_active_chars() {
+                let s: String = skeleton(&c.to_string()).collect();
+                assert!(skeletons.insert(s), "{:?}", c);
+            }
+        }
+    }
+
+    #[test]
+    fn unicode_versions_parse_with_or_without_zero_parts() {
+        let version: UnicodeVersion = "6".parse().unwrap();
+        assert_eq!(version.get_label(), "6.0");
+        assert!(
+            "6.0.0"
+                .parse::<UnicodeVersion>()
+                .is_ok_and(|v| v == version)
+        );
+        assert!("6.0.1".parse::<UnicodeVersion>().is_err());
+        assert!("1.0".parse::<UnicodeVersion>().is_err());
+    }
+
+    #[test]
+    fn code_points_newer_than_the_version_are_excluded() {
+        let mut config = Config::new();
+        // U+1F600 GRINNING FACE arrived in Unicode 6.1, U+263A WHITE SMILING FACE in 1.1.
+        assert!(is_active(&config, '\u{1F600}'));
+        config.set_max_unicode_version(Some("6.0".parse().unwrap()));
+        assert!(!is_active(&config, '\u{1F600}'));
+        assert!(is_active(&config, '\u{263A}'));
+        config.set_max_unicode_version(Some("6.1".parse().unwrap()));
+        assert!(is_active(&config, '\u{1F600}'));
+    }
+
+    #[test]
+    fn only_case_stable_characters_are_case_fold_safe() {
+        for c in ['a', '1', '!', '\u{3B1}', '\u{6F22}'] {
+            assert!(is_case_stable(c), "{:?}", c);
+        }
+        // Uppercase, titlecase, sharp s, long s, final sigma and dotless i all change or merge.
+        for c in ['A', '\u{1C5}', '\u{DF}', '\u{17F}', '\u{3C2}', '\u{131}'] {
+            assert!(!is_case_stable(c), "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn case_fold_safety_excludes_unstable_characters_and_passwords() {
+        let mut config = Config::new();
+        assert!(is_active(&config, 'A'));
+        config.set_case_fold_safe(true);
+        assert!(!is_active(&config, 'A'));
+        assert!(is_active(&config, 'a'));
+        assert!(is_active(&config, '1'));
+        assert!(config.passes_case_folding("abc123"));
+        assert!(!config.passes_case_folding("Abc123"));
+        config.set_case_fold_safe(false);
+        assert!(is_active(&config, 'A'));
+    }
+}
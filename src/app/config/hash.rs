@@ -0,0 +1,190 @@
+use std::str::FromStr;
+
+use rand::SeedableRng;
+
+use rand_chacha::ChaCha20Rng;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::config::{
+    Config, GenerationMode,
+    entropy::format_entropy,
+    length::LengthUnit,
+    rules::{ACCEPTANCE_SAMPLES, ACCEPTANCE_SEED},
+};
+
+// The password hash the server stores passwords with. Some of them only read the start of a
+// password and silently ignore the rest.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TargetHash {
+    #[default]
+    None,
+    Bcrypt,
+    Scrypt,
+    Argon2,
+    Pbkdf2,
+    Ntlm,
+}
+
+impl TargetHash {
+    pub fn get_label(&self) -> &'static str {
+        match self {
+            TargetHash::None => "None",
+            TargetHash::Bcrypt => "bcrypt",
+            TargetHash::Scrypt => "scrypt",
+            TargetHash::Argon2 => "Argon2",
+            TargetHash::Pbkdf2 => "PBKDF2",
+            TargetHash::Ntlm => "NTLM",
+        }
+    }
+
+    // How much of a password the hash reads, or `None` when it reads all of it. scrypt and
+    // PBKDF2 key HMAC with the password, which hashes long keys instead of cutting them, and
+    // Argon2 takes up to 2^32 - 1 bytes.
+    pub fn get_limit(&self) -> Option<(LengthUnit, usize)> {
+        match self {
+            TargetHash::Bcrypt => Some((LengthUnit::Utf8Bytes, 72)),
+            // Windows accepts at most 256 UTF-16 code units, 512 bytes of UTF-16LE for MD4.
+            TargetHash::Ntlm => Some((LengthUnit::Utf16Units, 256)),
+            TargetHash::None | TargetHash::Scrypt | TargetHash::Argon2 | TargetHash::Pbkdf2 => None,
+        }
+    }
+}
+
+impl FromStr for TargetHash {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(TargetHash::None),
+            "bcrypt" => Ok(TargetHash::Bcrypt),
+            "scrypt" => Ok(TargetHash::Scrypt),
+            "argon2" | "argon2id" => Ok(TargetHash::Argon2),
+            "pbkdf2" => Ok(TargetHash::Pbkdf2),
+            "ntlm" => Ok(TargetHash::Ntlm),
+            _ => Err(format!(
+                "unknown hash \"{}\" (expected none, bcrypt, scrypt, argon2, pbkdf2 or ntlm)",
+                s
+            )),
+        }
+    }
+}
+
+impl Config {
+    pub fn get_target_hash(&self) -> TargetHash {
+        self.target_hash
+    }
+
+    pub fn set_target_hash(&mut self, hash: TargetHash) {
+        self.target_hash = hash;
+    }
+
+    // The most units of `unit` a password can take up. Character passwords are bounded by the
+    // largest ratio of a character's width in `unit` to its width in the unit of the password
+    // length; other modes only have the longest of a sample of candidates.
+    pub fn get_max_units(&self, unit: LengthUnit) -> usize {
+        if self.generation_mode != GenerationMode::Characters {
+            return self.get_hash_coverage(unit, usize::MAX).0;
+        }
+        let length = self.get_password_length();
+        if unit == self.length_unit {
+            return length;
+        }
+        let ratio = self
+            .get_character_pools()
+            .iter()
+            .flat_map(|pool| &pool.chars)
+            .map(|c| unit.measure_char(*c) as f64 / self.length_unit.measure_char(*c) as f64)
+            .fold(0.0, f64::max);
+        (length as f64 * ratio) as usize
+    }
+
+    // The longest of a sample of candidates in `unit`, and the share of the entropy a hash
+    // reading the first `limit` of them keeps. Each candidate keeps the share of its units
+    // within the limit, which takes its entropy to be spread evenly over them.
+    fn get_hash_coverage(&self, unit: LengthUnit, limit: usize) -> (usize, f64) {
+        let mut rng = ChaCha20Rng::seed_from_u64(ACCEPTANCE_SEED);
+        let mut sample = self.get_candidate_sampler();
+        let mut longest = 0;
+        let mut kept = 0.0;
+        for _ in 0..ACCEPTANCE_SAMPLES {
+            let length = unit.measure(&sample(&mut rng));
+            longest = longest.max(length);
+            kept += match length > limit {
+                true => limit as f64 / length as f64,
+                false => 1.0,
+            };
+        }
+        (longest, kept / ACCEPTANCE_SAMPLES as f64)
+    }
+
+    // The entropy of the part of the password the target hash actually reads.
+    pub fn get_effective_entropy_bits(&self) -> f64 {
+        let bits = self.get_entropy_bits();
+        match self.target_hash.get_limit() {
+            Some((unit, limit)) => bits * self.get_hash_coverage(unit, limit).1,
+            None => bits,
+        }
+    }
+
+    // Explains how much the target hash cuts off, when passwords can be longer than it reads.
+    pub fn get_hash_warning(&self) -> Option<String> {
+        let (unit, limit) = self.target_hash.get_limit()?;
+        let longest = self.get_max_units(unit);
+        if longest <= limit {
+            return None;
+        }
+        let bits = self.get_entropy_bits();
+        Some(format!(
+            "{} only reads the first {} {}, but passwords can reach {}; about {} of the {} remain. Keep the password within {} {} to use all of it.",
+            self.target_hash.get_label(),
+            limit,
+            unit.get_label(),
+            longest,
+            format_entropy(self.get_effective_entropy_bits()),
+            format_entropy(bits),
+            limit,
+            unit.get_label()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TargetHash;
+    use crate::app::config::{Config, GenerationMode, length::LengthUnit};
+
+    #[test]
+    fn long_passwords_warn_instead_of_failing_the_policy() {
+        for mode in [
+            GenerationMode::Characters,
+            GenerationMode::Passphrase,
+            GenerationMode::Pronounceable,
+        ] {
+            let mut config = Config::new();
+            config.set_generation_mode(mode);
+            config.set_password_length(128);
+            config.set_word_count(40);
+            config.set_syllable_count(40);
+            config.set_target_hash(TargetHash::Bcrypt);
+            assert!(config.validate_policy().is_ok());
+            assert!(config.get_max_units(LengthUnit::Utf8Bytes) > 72);
+            assert!(config.get_hash_warning().is_some());
+            assert!(config.get_effective_entropy_bits() < config.get_entropy_bits());
+        }
+    }
+
+    #[test]
+    fn passwords_within_the_limit_keep_all_their_entropy() {
+        let mut config = Config::new();
+        config.set_length_unit(LengthUnit::Utf8Bytes);
+        config.set_password_length(72);
+        config.set_target_hash(TargetHash::Bcrypt);
+        assert!(config.validate_policy().is_ok());
+        assert!(config.get_hash_warning().is_none());
+        assert_eq!(
+            config.get_effective_entropy_bits(),
+            config.get_entropy_bits()
+        );
+    }
+}
@@ -0,0 +1,741 @@
+use std::{ops::RangeInclusive, str::FromStr};
+
+use rand::{
+    distr::{Distribution, weighted::WeightedIndex},
+    seq::{IndexedRandom, SliceRandom},
+};
+
+use rand_chacha::ChaCha20Rng;
+
+use serde::{Deserialize, Serialize};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::app::config::{Config, SamplingMode, entropy::EntropyPool, pool::CharacterPool};
+
+// What the password length counts. Systems limit passwords by different measures: bcrypt reads
+// at most 72 UTF-8 bytes, Windows counts UTF-16 code units and people count what they see.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LengthUnit {
+    #[default]
+    CodePoints,
+    Utf8Bytes,
+    Utf16Units,
+    Graphemes,
+}
+
+impl LengthUnit {
+    pub const ALL: [LengthUnit; 4] = [
+        LengthUnit::CodePoints,
+        LengthUnit::Utf8Bytes,
+        LengthUnit::Utf16Units,
+        LengthUnit::Graphemes,
+    ];
+
+    pub fn get_label(&self) -> &'static str {
+        match self {
+            LengthUnit::CodePoints => "Code Points",
+            LengthUnit::Utf8Bytes => "UTF-8 Bytes",
+            LengthUnit::Utf16Units => "UTF-16 Code Units",
+            LengthUnit::Graphemes => "Grapheme Clusters",
+        }
+    }
+
+    pub fn measure(&self, s: &str) -> usize {
+        match self {
+            LengthUnit::CodePoints => s.chars().count(),
+            LengthUnit::Utf8Bytes => s.len(),
+            LengthUnit::Utf16Units => s.encode_utf16().count(),
+            LengthUnit::Graphemes => s.graphemes(true).count(),
+        }
+    }
+
+    // Generated passwords break between every two code points, so a length in grapheme
+    // clusters is a length in code points.
+    pub fn counts_code_points(&self) -> bool {
+        matches!(self, LengthUnit::CodePoints | LengthUnit::Graphemes)
+    }
+
+    pub fn measure_char(&self, c: char) -> usize {
+        match self {
+            LengthUnit::CodePoints | LengthUnit::Graphemes => 1,
+            LengthUnit::Utf8Bytes => c.len_utf8(),
+            LengthUnit::Utf16Units => c.len_utf16(),
+        }
+    }
+
+    // The most units a single code point can take up.
+    pub fn get_max_units_per_char(&self) -> usize {
+        match self {
+            LengthUnit::CodePoints | LengthUnit::Graphemes => 1,
+            LengthUnit::Utf8Bytes => 4,
+            LengthUnit::Utf16Units => 2,
+        }
+    }
+}
+
+impl FromStr for LengthUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "chars" | "code-points" => Ok(LengthUnit::CodePoints),
+            "bytes" | "utf8" => Ok(LengthUnit::Utf8Bytes),
+            "utf16" => Ok(LengthUnit::Utf16Units),
+            "graphemes" => Ok(LengthUnit::Graphemes),
+            _ => Err(format!(
+                "unknown length unit \"{}\" (expected chars, bytes, utf16 or graphemes)",
+                s
+            )),
+        }
+    }
+}
+
+// Every measurement of `password`, for display.
+pub fn get_length_summary(password: &str) -> String {
+    LengthUnit::ALL
+        .iter()
+        .map(|unit| format!("{} {}", unit.measure(password), unit.get_label()))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// The most units a code point takes up in any length unit.
+const MAX_WIDTH: usize = 4;
+
+// The share of `chars` that take up each number of units.
+fn get_width_distribution(chars: &[char], unit: LengthUnit) -> Vec<f64> {
+    let mut distribution = vec![0.0; MAX_WIDTH + 1];
+    for c in chars {
+        distribution[unit.measure_char(*c)] += 1.0 / chars.len() as f64;
+    }
+    distribution
+}
+
+// Convolves a distribution over total widths with that of one more character, dropping the
+// widths beyond the end of `total`.
+fn convolve(total: &[f64], width: &[f64]) -> Vec<f64> {
+    let mut result = vec![0.0; total.len()];
+    for (i, a) in total.iter().enumerate().filter(|(_, a)| **a > 0.0) {
+        for (w, b) in width.iter().enumerate().take(total.len() - i) {
+            result[i + w] += a * b;
+        }
+    }
+    result
+}
+
+// Widths add up over code points, and without maximums every draw is independent of the others.
+fn draws_exact_lengths(pools: &[CharacterPool]) -> bool {
+    pools.iter().all(|pool| pool.max.is_none())
+}
+
+// The probability of `k` successes in `n` trials that each succeed with probability `q`.
+fn binomial(log_fact: &[f64], n: usize, k: usize, q: f64) -> f64 {
+    if q <= 0.0 {
+        return (k == 0) as u8 as f64;
+    }
+    if q >= 1.0 {
+        return (k == n) as u8 as f64;
+    }
+    (log_fact[n] - log_fact[k] - log_fact[n - k]
+        + k as f64 * q.ln()
+        + (n - k) as f64 * (1.0 - q).ln())
+    .exp()
+}
+
+// A code point count that can add up to the password length.
+pub struct CharCount {
+    pub count: usize,
+    // The log2 of how many passwords of this count measure the password length.
+    pub bits: f64,
+    // How likely each restricted pool set is to have drawn such a password.
+    set_weights: Vec<f64>,
+}
+
+// A pool whose maximum limits how many extra characters it supplies.
+struct CappedPool {
+    index: usize,
+    cap: usize,
+    // Its share of the extra characters supplied by itself, the unlimited pools and the capped
+    // pools before it.
+    share: f64,
+}
+
+// The pools of one pool set with their characters grouped by width, for drawing passwords of an
+// exact length without rejecting any.
+struct WidthPools {
+    // The characters of each pool, indexed by width.
+    chars: Vec<Vec<Vec<char>>>,
+    widths: Vec<Vec<f64>>,
+    mins: Vec<usize>,
+    // How likely each pool whose cap never binds is to supply an extra character, zero for the
+    // others, and the width of such a character.
+    masses: Vec<f64>,
+    extra: Vec<f64>,
+    capped: Vec<CappedPool>,
+    // With capped pools, `stages[k][t]` is the distribution of the total width of the minimums
+    // and t extra characters, given that these all come from the unlimited pools and the first
+    // k capped pools and stay within their caps, and `held[t]` is the probability that t extra
+    // characters from every pool stay within the caps.
+    stages: Vec<Vec<Vec<f64>>>,
+    held: Vec<f64>,
+    log_fact: Vec<f64>,
+}
+
+impl WidthPools {
+    // The exact share of candidates of each count in `counts` that measure `length` units, from
+    // the tables of the capped pools.
+    fn get_match_rates(&self, counts: RangeInclusive<usize>, length: usize) -> Vec<f64> {
+        let minimum: usize = self.mins.iter().sum();
+        let last = self.stages.last().unwrap();
+        counts
+            .map(|count| match count.checked_sub(minimum) {
+                Some(t) if t < last.len() && self.held[t] > 0.0 => last[t][length] / self.held[t],
+                _ => 0.0,
+            })
+            .collect()
+    }
+
+    // Picks how many extra characters each capped pool supplies to a password of `extra` extra
+    // characters and `length` units, from the last capped pool to the first, each in proportion
+    // to how likely it leaves the rest to make up the length.
+    fn get_capped_counts(
+        &self,
+        mut extra: usize,
+        length: usize,
+        rng: &mut ChaCha20Rng,
+    ) -> Vec<usize> {
+        let mut counts = vec![0; self.mins.len()];
+        // The distribution of the total width of the capped characters picked so far.
+        let mut picked = vec![0.0; length + 1];
+        picked[0] = 1.0;
+        for (k, pool) in self.capped.iter().enumerate().rev() {
+            let mut options = Vec::new();
+            let mut width = picked;
+            for e in 0..=pool.cap.min(extra) {
+                let rest = &self.stages[k][extra - e];
+                let fits: f64 = width
+                    .iter()
+                    .zip(rest.iter().rev())
+                    .map(|(a, b)| a * b)
+                    .sum();
+                let next = convolve(&width, &self.widths[pool.index]);
+                options.push((binomial(&self.log_fact, extra, e, pool.share) * fits, width));
+                width = next;
+            }
+            let e = WeightedIndex::new(options.iter().map(|(weight, _)| *weight))
+                .unwrap()
+                .sample(rng);
+            counts[pool.index] = e;
+            extra -= e;
+            picked = options.swap_remove(e).1;
+        }
+        counts
+    }
+
+    // Makes the same draws as `generate_character_password`, conditioned on their widths adding
+    // up to `length`: the split of the extra characters between the capped pools is picked
+    // first, then the width of each draw in proportion to how likely the remaining draws are to
+    // make up the rest, then a character of that width.
+    fn generate(&self, count: usize, length: usize, rng: &mut ChaCha20Rng) -> String {
+        let minimum: usize = self.mins.iter().sum();
+        let capped = self.get_capped_counts(count - minimum, length, rng);
+        let mut draws: Vec<Option<usize>> = self
+            .mins
+            .iter()
+            .zip(&capped)
+            .enumerate()
+            .flat_map(|(i, (min, extra))| std::iter::repeat_n(Some(i), min + extra))
+            .collect();
+        draws.resize(count, None);
+        let widths: Vec<&Vec<f64>> = draws
+            .iter()
+            .map(|draw| match draw {
+                Some(i) => &self.widths[*i],
+                None => &self.extra,
+            })
+            .collect();
+
+        // The distribution of the total width of every draw from each one onwards.
+        let mut remaining = vec![vec![0.0; length + 1]; count + 1];
+        remaining[count][0] = 1.0;
+        for k in (0..count).rev() {
+            remaining[k] = convolve(&remaining[k + 1], widths[k]);
+        }
+
+        let mut left = length;
+        let mut result: Vec<char> = Vec::with_capacity(count);
+        for (k, draw) in draws.iter().enumerate() {
+            let width = WeightedIndex::new(
+                (0..=MAX_WIDTH.min(left)).map(|w| widths[k][w] * remaining[k + 1][left - w]),
+            )
+            .unwrap()
+            .sample(rng);
+            left -= width;
+            let pool = match draw {
+                Some(i) => *i,
+                None => {
+                    WeightedIndex::new(self.chars.iter().zip(&self.masses).map(|(chars, mass)| {
+                        let total: usize = chars.iter().map(Vec::len).sum();
+                        mass * chars[width].len() as f64 / total as f64
+                    }))
+                    .unwrap()
+                    .sample(rng)
+                }
+            };
+            result.push(*self.chars[pool][width].choose(rng).unwrap());
+        }
+        result.shuffle(rng);
+        result.into_iter().collect()
+    }
+}
+
+impl Config {
+    pub fn get_length_unit(&self) -> LengthUnit {
+        self.length_unit
+    }
+
+    pub fn set_length_unit(&mut self, unit: LengthUnit) {
+        self.length_unit = unit;
+    }
+
+    // The code point counts that could add up to `length` bytes or UTF-16 units.
+    fn get_char_count_range(
+        &self,
+        pool_sets: &[Vec<CharacterPool>],
+        length: usize,
+    ) -> (usize, usize) {
+        let unit = self.length_unit;
+        let widths: Vec<usize> = pool_sets
+            .iter()
+            .flatten()
+            .flat_map(|pool| pool.chars.iter().map(|c| unit.measure_char(*c)))
+            .collect();
+        let narrowest = widths.iter().copied().min().unwrap_or(1);
+        let widest = widths.iter().copied().max().unwrap_or(1);
+        let (lo, hi) = (length.div_ceil(widest), length / narrowest);
+        let minimum = self.get_min_required_chars_for_active_categories();
+        let maximum = self
+            .get_max_total_chars_for_active_categories()
+            .unwrap_or(usize::MAX);
+        (lo.max(minimum), hi.min(maximum))
+    }
+
+    // How likely `pool` is to supply an extra character, relative to the other pools.
+    fn get_extra_mass(&self, pool: &CharacterPool) -> f64 {
+        match (self.pool_takes_extra_chars(pool), self.sampling_mode) {
+            (false, _) => 0.0,
+            (true, SamplingMode::Uniform) => pool.chars.len() as f64,
+            (true, SamplingMode::Weighted) => pool.weight as f64,
+        }
+    }
+
+    // The distribution of the width of one character drawn from the extra characters of `pools`,
+    // as `generate_extra_chars` draws them from the pools without a maximum that binds.
+    fn get_extra_width_distribution(&self, pools: &[&CharacterPool]) -> Vec<f64> {
+        let total: f64 = pools.iter().map(|pool| self.get_extra_mass(pool)).sum();
+        let mut distribution = vec![0.0; MAX_WIDTH + 1];
+        for pool in pools.iter().filter(|_| total > 0.0) {
+            let width = get_width_distribution(&pool.chars, self.length_unit);
+            for (w, p) in width.iter().enumerate() {
+                distribution[w] += p * self.get_extra_mass(pool) / total;
+            }
+        }
+        distribution
+    }
+
+    // The exact share of candidates of each count in `counts` that measure `length` units, for
+    // units that add up over code points. Without maximums each pool's minimum and every extra
+    // character is an independent draw, so the total width is a convolution of their width
+    // distributions.
+    fn get_exact_length_match_rates(
+        &self,
+        pools: &[CharacterPool],
+        counts: RangeInclusive<usize>,
+        length: usize,
+    ) -> Vec<f64> {
+        if !draws_exact_lengths(pools) {
+            return self
+                .get_width_pools(pools, length, *counts.end())
+                .get_match_rates(counts, length);
+        }
+        let unit = self.length_unit;
+        let mut total = vec![0.0; length + 1];
+        total[0] = 1.0;
+        for pool in pools {
+            let width = get_width_distribution(&pool.chars, unit);
+            for _ in 0..pool.min {
+                total = convolve(&total, &width);
+            }
+        }
+        let extra = self.get_extra_width_distribution(&pools.iter().collect::<Vec<_>>());
+        let mut drawn: usize = pools.iter().map(|pool| pool.min).sum();
+        counts
+            .map(|count| {
+                if count < drawn {
+                    return 0.0;
+                }
+                while drawn < count {
+                    total = convolve(&total, &extra);
+                    drawn += 1;
+                }
+                total[length]
+            })
+            .collect()
+    }
+
+    // Each code point count that can produce a password of `length` units, with the entropy of
+    // the passwords it holds.
+    pub fn get_char_counts(&self, length: usize) -> Vec<CharCount> {
+        let pool_sets = self.get_restricted_pool_sets();
+        let entropy_pool_sets: Vec<Vec<EntropyPool>> = pool_sets
+            .iter()
+            .map(|pools| self.get_entropy_pools(pools))
+            .collect();
+        let (lo, hi) = self.get_char_count_range(&pool_sets, length);
+        if lo > hi {
+            return Vec::new();
+        }
+
+        let rates: Vec<Vec<f64>> = pool_sets
+            .iter()
+            .map(|pools| self.get_exact_length_match_rates(pools, lo..=hi, length))
+            .collect();
+        // The entropy of every count at once, which shares the work between them.
+        let set_bits: Vec<Vec<f64>> = entropy_pool_sets
+            .iter()
+            .map(|pools| self.get_drawn_entropy_bits_for_lengths(pools, lo..=hi))
+            .collect();
+        let mut counts = Vec::new();
+        for (i, count) in (lo..=hi).enumerate() {
+            let bits: Vec<Option<f64>> = entropy_pool_sets
+                .iter()
+                .zip(&set_bits)
+                .map(|(pools, bits)| self.fits_pools(pools, count).then_some(bits[i]))
+                .collect();
+            let probabilities = self.get_pool_set_probabilities(&bits);
+            let set_weights: Vec<f64> = probabilities
+                .iter()
+                .zip(&rates)
+                .map(|(p, rates)| p * rates[i])
+                .collect();
+            let rate: f64 = set_weights.iter().sum();
+            if rate > 0.0 {
+                let entropy: f64 = probabilities
+                    .iter()
+                    .zip(&bits)
+                    .map(|(p, bits)| p * bits.unwrap_or(0.0))
+                    .sum();
+                counts.push(CharCount {
+                    count,
+                    bits: entropy + rate.log2(),
+                    set_weights,
+                });
+            }
+        }
+        counts
+    }
+
+    // Passwords of different code point counts never coincide, so picking a count in proportion
+    // to 2^entropy of its passwords gives the log2 of the sum of those powers.
+    pub fn get_length_unit_entropy_bits(&self, length: usize) -> f64 {
+        let counts = self.get_char_counts(length);
+        let max = counts
+            .iter()
+            .map(|count| count.bits)
+            .fold(f64::NEG_INFINITY, f64::max);
+        match counts.is_empty() {
+            true => 0.0,
+            false => {
+                max + counts
+                    .iter()
+                    .map(|count| (count.bits - max).exp2())
+                    .sum::<f64>()
+                    .log2()
+            }
+        }
+    }
+
+    // Picks a code point count first, then a password of that count that measures exactly the
+    // password length, drawn directly.
+    pub fn get_length_unit_sampler(
+        &self,
+        length: usize,
+    ) -> Box<dyn FnMut(&mut ChaCha20Rng) -> String + '_> {
+        let counts = self.get_char_counts(length);
+        let max = counts
+            .iter()
+            .map(|count| count.bits)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let index = WeightedIndex::new(counts.iter().map(|count| (count.bits - max).exp2())).ok();
+        let longest = counts.last().map_or(0, |count| count.count);
+        let width_pool_sets: Vec<WidthPools> = self
+            .get_restricted_pool_sets()
+            .iter()
+            .map(|pools| self.get_width_pools(pools, length, longest))
+            .collect();
+        Box::new(move |rng| match &index {
+            Some(index) => {
+                let count = &counts[index.sample(rng)];
+                let set = WeightedIndex::new(&count.set_weights).unwrap().sample(rng);
+                width_pool_sets[set].generate(count.count, length, rng)
+            }
+            None => String::new(),
+        })
+    }
+
+    // Groups the characters of `pools` by width for passwords of `length` units and up to
+    // `longest` code points. With maximums, the number of extra characters each capped pool
+    // supplies follows a multinomial distribution conditioned on the caps, as in
+    // `generate_extra_chars`, so the width tables are built one capped pool at a time: each adds
+    // a binomial share of the extra characters to every row, at the cost of one width
+    // convolution per row and character it can take. Pools whose cap never binds count as
+    // unlimited.
+    fn get_width_pools(
+        &self,
+        pools: &[CharacterPool],
+        length: usize,
+        longest: usize,
+    ) -> WidthPools {
+        let unit = self.length_unit;
+        let minimum: usize = pools.iter().map(|pool| pool.min).sum();
+        let most = longest.saturating_sub(minimum);
+        let binds = |pool: &CharacterPool| pool.get_extra_cap().is_some_and(|cap| cap < most);
+        let unlimited: Vec<&CharacterPool> = pools.iter().filter(|pool| !binds(pool)).collect();
+        let widths: Vec<Vec<f64>> = pools
+            .iter()
+            .map(|pool| get_width_distribution(&pool.chars, unit))
+            .collect();
+        let extra = self.get_extra_width_distribution(&unlimited);
+        let mut log_fact = vec![0.0; most + 1];
+        for k in 1..=most {
+            log_fact[k] = log_fact[k - 1] + (k as f64).ln();
+        }
+
+        let mut capped = Vec::new();
+        let mut stages = Vec::new();
+        let mut held = Vec::new();
+        if !draws_exact_lengths(pools) {
+            let mut rows = vec![vec![0.0; length + 1]];
+            rows[0][0] = 1.0;
+            for (pool, width) in pools.iter().zip(&widths) {
+                for _ in 0..pool.min {
+                    rows[0] = convolve(&rows[0], width);
+                }
+            }
+            let mut mass: f64 = unlimited.iter().map(|pool| self.get_extra_mass(pool)).sum();
+            held.push(1.0);
+            for t in 1..=most {
+                let row = match mass > 0.0 {
+                    true => convolve(&rows[t - 1], &extra),
+                    false => vec![0.0; length + 1],
+                };
+                rows.push(row);
+                held.push((mass > 0.0) as u8 as f64);
+            }
+            stages.push(rows);
+
+            for (index, pool) in pools.iter().enumerate().filter(|(_, pool)| binds(pool)) {
+                let pool_mass = self.get_extra_mass(pool);
+                if pool_mass == 0.0 {
+                    continue;
+                }
+                mass += pool_mass;
+                let pool = CappedPool {
+                    index,
+                    cap: pool.get_extra_cap().unwrap_or(0),
+                    share: pool_mass / mass,
+                };
+                let rows = stages.last().unwrap();
+                let mut next = vec![vec![0.0; length + 1]; most + 1];
+                let mut next_held = vec![0.0; most + 1];
+                for (t, row) in rows.iter().enumerate() {
+                    let mut row = row.clone();
+                    for e in 0..=pool.cap.min(most - t) {
+                        let share = binomial(&log_fact, t + e, e, pool.share);
+                        for (a, b) in next[t + e].iter_mut().zip(&row) {
+                            *a += share * b;
+                        }
+                        next_held[t + e] += share * held[t];
+                        row = convolve(&row, &widths[index]);
+                    }
+                }
+                stages.push(next);
+                held = next_held;
+                capped.push(pool);
+            }
+        }
+
+        WidthPools {
+            chars: pools
+                .iter()
+                .map(|pool| {
+                    let mut chars = vec![Vec::new(); MAX_WIDTH + 1];
+                    for c in &pool.chars {
+                        chars[unit.measure_char(*c)].push(*c);
+                    }
+                    chars
+                })
+                .collect(),
+            widths,
+            mins: pools.iter().map(|pool| pool.min).collect(),
+            masses: pools
+                .iter()
+                .map(|pool| match binds(pool) {
+                    true => 0.0,
+                    false => self.get_extra_mass(pool),
+                })
+                .collect(),
+            extra,
+            capped,
+            stages,
+            held,
+            log_fact,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use rand::SeedableRng;
+
+    use rand_chacha::ChaCha20Rng;
+
+    use super::{LengthUnit, draws_exact_lengths};
+    use crate::app::config::{Config, SamplingMode, category::CodePointCategory};
+
+    // Categories of one to four UTF-8 bytes and one or two UTF-16 code units per code point.
+    fn mixed_width_config() -> Config {
+        let mut config = Config::new();
+        for cat in config.categories.iter_mut() {
+            cat.set_enabled(false);
+        }
+        for name in [
+            "ASCII Digits",
+            "IPA Extended Characters and Modifier Letters",
+            "Indian Characters",
+            "Linear A and Linear B Characters",
+        ] {
+            let cat = config.get_categories_by_name_mut(name).next().unwrap();
+            cat.set_enabled(true);
+            cat.set_min_required_chars(1);
+        }
+        config
+    }
+
+    fn digits(config: &mut Config) -> &mut CodePointCategory {
+        config
+            .get_categories_by_name_mut("ASCII Digits")
+            .next()
+            .unwrap()
+    }
+
+    #[test]
+    fn width_pools_fill_the_exact_length() {
+        let mut config = mixed_width_config();
+        let widths: BTreeSet<usize> = config
+            .get_character_pools()
+            .iter()
+            .flat_map(|pool| &pool.chars)
+            .map(|c| c.len_utf8())
+            .collect();
+        assert_eq!(widths, BTreeSet::from([1, 2, 3, 4]));
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        for (mode, max) in [
+            (SamplingMode::Uniform, None),
+            (SamplingMode::Weighted, None),
+            (SamplingMode::Uniform, Some(2)),
+            (SamplingMode::Weighted, Some(2)),
+        ] {
+            config.set_sampling_mode(mode);
+            digits(&mut config).set_max_allowed_chars(max);
+            for (unit, length) in [(LengthUnit::Utf8Bytes, 23), (LengthUnit::Utf16Units, 11)] {
+                config.set_length_unit(unit);
+                let pool_sets = config.get_restricted_pool_sets();
+                assert_eq!(draws_exact_lengths(&pool_sets[0]), max.is_none());
+                let counts = config.get_char_counts(length);
+                assert!(!counts.is_empty());
+                let longest = counts.last().unwrap().count;
+                let width_pools = config.get_width_pools(&pool_sets[0], length, longest);
+                for count in counts {
+                    for _ in 0..200 {
+                        let password = width_pools.generate(count.count, length, &mut rng);
+                        assert_eq!(unit.measure(&password), length, "{}", password);
+                        assert_eq!(password.chars().count(), count.count, "{}", password);
+                        for cat in config.get_active_categories() {
+                            assert!(
+                                password.chars().any(|c| cat.has_active_char(c)),
+                                "{} has nothing from {}",
+                                password,
+                                cat.get_name()
+                            );
+                        }
+                        let digit_count = password.chars().filter(char::is_ascii_digit).count();
+                        assert!(max.is_none_or(|max| digit_count <= max), "{}", password);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generated_passwords_measure_the_password_length() {
+        let mut config = mixed_width_config();
+        config.set_password_length(20);
+        for unit in LengthUnit::ALL {
+            config.set_length_unit(unit);
+            for _ in 0..200 {
+                let password = config.generate_password();
+                assert_eq!(unit.measure(&password), 20, "{}", password);
+            }
+        }
+    }
+
+    #[test]
+    fn capped_length_rates_match_the_candidates() {
+        let mut config = mixed_width_config();
+        digits(&mut config).set_max_allowed_chars(Some(2));
+        config.set_length_unit(LengthUnit::Utf8Bytes);
+        let length = 23;
+        let pools = config.get_character_pools();
+        let (lo, hi) = config.get_char_count_range(std::slice::from_ref(&pools), length);
+        let rates = config.get_exact_length_match_rates(&pools, lo..=hi, length);
+
+        // 20,000 candidates give a standard deviation below 0.0036 for any rate.
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let samples = 20_000;
+        for (count, rate) in (lo..=hi).zip(rates).filter(|(_, rate)| *rate > 0.05) {
+            let matched = (0..samples)
+                .filter(|_| {
+                    let candidate = config.generate_character_password(&pools, count, &mut rng);
+                    LengthUnit::Utf8Bytes.measure(&candidate) == length
+                })
+                .count();
+            let share = matched as f64 / samples as f64;
+            assert!(
+                (share - rate).abs() < 0.02,
+                "{} code points: {} of candidates match, expected {}",
+                count,
+                share,
+                rate
+            );
+        }
+    }
+
+    #[test]
+    fn long_capped_passwords_are_drawn_directly() {
+        let mut config = Config::new();
+        digits(&mut config).set_max_allowed_chars(Some(3));
+        config.set_length_unit(LengthUnit::Utf16Units);
+        config.set_password_length(200);
+        let mut sample = config.get_length_unit_sampler(config.get_password_length());
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        for _ in 0..20 {
+            let password = sample(&mut rng);
+            assert_eq!(LengthUnit::Utf16Units.measure(&password), 200);
+            assert!(password.chars().filter(char::is_ascii_digit).count() <= 3);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::str::FromStr;
+
+use seshat::unicode::{
+    CodePoint, Ucd,
+    props::{Gc, Hst},
+};
+
+use serde::{Deserialize, Serialize};
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::app::config::Config;
+
+// How the server that stores the password normalizes it. Characters it would change are kept out
+// of the pools, and whole passwords are checked too, since neighbouring characters can compose.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NormalizationForm {
+    #[default]
+    None,
+    Nfc,
+    Nfkc,
+    // RFC 8265: the FreeformClass of RFC 8264, non-ASCII spaces mapped to U+0020, then NFC.
+    PrecisOpaqueString,
+}
+
+impl NormalizationForm {
+    pub fn get_label(&self) -> &'static str {
+        match self {
+            NormalizationForm::None => "None",
+            NormalizationForm::Nfc => "NFC",
+            NormalizationForm::Nfkc => "NFKC",
+            NormalizationForm::PrecisOpaqueString => "PRECIS OpaqueString",
+        }
+    }
+
+    pub fn normalize(&self, s: &str) -> String {
+        match self {
+            NormalizationForm::None => s.to_string(),
+            NormalizationForm::Nfc => s.nfc().collect(),
+            NormalizationForm::Nfkc => s.nfkc().collect(),
+            NormalizationForm::PrecisOpaqueString => s
+                .chars()
+                .map(|c| match is_non_ascii_space(c) {
+                    true => ' ',
+                    false => c,
+                })
+                .nfc()
+                .collect(),
+        }
+    }
+
+    // Whether a password made of `c` alone is stored unchanged and, for PRECIS, allowed at all.
+    pub fn is_stable(&self, c: char) -> bool {
+        let s = c.to_string();
+        self.normalize(&s) == s
+            && (*self != NormalizationForm::PrecisOpaqueString || is_precis_freeform(c))
+    }
+}
+
+impl FromStr for NormalizationForm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(NormalizationForm::None),
+            "nfc" => Ok(NormalizationForm::Nfc),
+            "nfkc" => Ok(NormalizationForm::Nfkc),
+            "precis" | "opaquestring" => Ok(NormalizationForm::PrecisOpaqueString),
+            _ => Err(format!(
+                "unknown normalization form \"{}\" (expected none, nfc, nfkc or precis)",
+                s
+            )),
+        }
+    }
+}
+
+fn is_non_ascii_space(c: char) -> bool {
+    c != ' ' && CodePoint::new(c as u32).is_ok_and(|cp| cp.gc() == Gc::Zs)
+}
+
+// The code points the FreeformClass disallows: controls, surrogates, unassigned, private use and
+// default ignorable code points, line and paragraph separators, old Hangul jamo, and format
+// characters without a compatibility decomposition. Everything else is either valid or allowed
+// through the FreeformClass's broader categories, apart from the few code points RFC 5892 lists
+// as exceptions, which are not checked.
+fn is_precis_freeform(c: char) -> bool {
+    CodePoint::new(c as u32).is_ok_and(|cp| {
+        let has_compat = !c.to_string().nfkc().eq([c]);
+        !matches!(cp.gc(), Gc::Cc | Gc::Cs | Gc::Cn | Gc::Co | Gc::Zl | Gc::Zp)
+            && (cp.gc() != Gc::Cf || has_compat)
+            && !cp.di()
+            && !matches!(cp.hst(), Hst::L | Hst::V | Hst::T)
+    })
+}
+
+impl Config {
+    pub fn get_normalization_form(&self) -> NormalizationForm {
+        self.normalization
+    }
+
+    pub fn set_normalization_form(&mut self, form: NormalizationForm) {
+        self.normalization = form;
+        self.update_exclusions();
+    }
+
+    pub fn passes_normalization(&self, password: &str) -> bool {
+        self.normalization == NormalizationForm::None
+            || self.normalization.normalize(password) == password
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NormalizationForm;
+    use crate::app::config::Config;
+
+    #[test]
+    fn nfc_and_nfkc_keep_only_stable_characters() {
+        let nfc = NormalizationForm::Nfc;
+        let nfkc = NormalizationForm::Nfkc;
+        assert!(nfc.is_stable('\u{E9}') && nfkc.is_stable('\u{E9}'));
+        // The Angstrom sign is canonically equivalent to Å, the fi ligature only compatibly to fi.
+        assert!(!nfc.is_stable('\u{212B}'));
+        assert!(nfc.is_stable('\u{FB01}') && !nfkc.is_stable('\u{FB01}'));
+    }
+
+    #[test]
+    fn whole_passwords_are_checked_for_composition() {
+        let mut config = Config::new();
+        assert!(config.passes_normalization("e\u{301}"));
+        config.set_normalization_form(NormalizationForm::Nfc);
+        assert!(!config.passes_normalization("e\u{301}"));
+        assert!(config.passes_normalization("\u{E9}"));
+    }
+
+    #[test]
+    fn opaque_string_maps_spaces_and_disallows_what_freeform_does() {
+        let precis = NormalizationForm::PrecisOpaqueString;
+        assert_eq!(precis.normalize("a\u{A0}b"), "a b");
+        assert!(precis.is_stable('a') && precis.is_stable(' ') && precis.is_stable('\u{20AC}'));
+        for c in [
+            '\u{7}',    // control
+            '\u{A0}',   // non-ASCII space
+            '\u{200B}', // default ignorable
+            '\u{600}',  // format character
+            '\u{E000}', // private use
+            '\u{2028}', // line separator
+            '\u{2029}', // paragraph separator
+            '\u{1100}', // old Hangul jamo
+        ] {
+            assert!(!precis.is_stable(c), "{:?}", c);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::str::FromStr;
+
+use rand::Rng;
+
+use rand_chacha::ChaCha20Rng;
+
+use serde::{Deserialize, Serialize};
+
+use passgen::{generate_random_chars_from_char_vec, generate_random_words_from_word_vec};
+
+use crate::app::config::{Config, category::CodePointCategory, entropy::reaches_entropy};
+
+const EFF_LARGE_WORDLIST: &str = include_str!("passphrase/eff_large_wordlist.txt");
+const EFF_SHORT_WORDLIST: &str = include_str!("passphrase/eff_short_wordlist.txt");
+
+const DEFAULT_WORD_COUNT: usize = 6;
+const DEFAULT_SEPARATOR: &str = "-";
+const MAX_DERIVED_WORD_COUNT: usize = 1 << 10;
+const DIGITS: &str = "0123456789";
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Wordlist {
+    // 7776 words, one per roll of five dice.
+    #[default]
+    EffLarge,
+    // 1296 shorter words, one per roll of four dice.
+    EffShort,
+}
+
+impl Wordlist {
+    pub fn get_label(&self) -> &str {
+        match self {
+            Wordlist::EffLarge => "EFF Large",
+            Wordlist::EffShort => "EFF Short",
+        }
+    }
+
+    // The lists are stored in the EFF's own format: the dice roll, a tab, then the word.
+    pub fn get_words(&self) -> Vec<&'static str> {
+        let list = match self {
+            Wordlist::EffLarge => EFF_LARGE_WORDLIST,
+            Wordlist::EffShort => EFF_SHORT_WORDLIST,
+        };
+        list.lines()
+            .filter_map(|line| line.split_once('\t').map(|(_, word)| word))
+            .collect()
+    }
+}
+
+impl FromStr for Wordlist {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "large" | "eff-large" => Ok(Wordlist::EffLarge),
+            "short" | "eff-short" => Ok(Wordlist::EffShort),
+            _ => Err(format!(
+                "unknown wordlist \"{}\" (expected large or short)",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Capitalization {
+    #[default]
+    Lowercase,
+    // The first letter of every word.
+    Words,
+    // The first letter of each word with a one in two chance, adding one bit per word.
+    Random,
+    Uppercase,
+}
+
+impl Capitalization {
+    pub fn get_label(&self) -> &'static str {
+        match self {
+            Capitalization::Lowercase => "Lowercase",
+            Capitalization::Words => "Capitalize Words",
+            Capitalization::Random => "Capitalize Random Words",
+            Capitalization::Uppercase => "Uppercase",
+        }
+    }
+}
+
+impl FromStr for Capitalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "lower" | "lowercase" | "none" => Ok(Capitalization::Lowercase),
+            "words" => Ok(Capitalization::Words),
+            "random" => Ok(Capitalization::Random),
+            "upper" | "uppercase" => Ok(Capitalization::Uppercase),
+            _ => Err(format!(
+                "unknown capitalization \"{}\" (expected lower, words, random or upper)",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PassphraseOptions {
+    pub wordlist: Wordlist,
+    pub word_count: usize,
+    // Placed between words, unless `separator_category` is set.
+    pub separator: String,
+    // Draw every separator from the active code points of this category instead.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub separator_category: Option<String>,
+    pub capitalization: Capitalization,
+    // Random digits appended to one randomly chosen word.
+    pub digits: usize,
+}
+
+impl Default for PassphraseOptions {
+    fn default() -> Self {
+        PassphraseOptions {
+            wordlist: Wordlist::default(),
+            word_count: DEFAULT_WORD_COUNT,
+            separator: DEFAULT_SEPARATOR.to_string(),
+            separator_category: None,
+            capitalization: Capitalization::default(),
+            digits: 0,
+        }
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+impl Config {
+    pub fn get_passphrase_options(&self) -> &PassphraseOptions {
+        &self.passphrase
+    }
+
+    pub fn get_passphrase_options_mut(&mut self) -> &mut PassphraseOptions {
+        &mut self.passphrase
+    }
+
+    // The separator category, if it is enabled and has active code points to draw from.
+    pub fn get_separator_category(&self) -> Option<&CodePointCategory> {
+        self.get_active_category_by_name(self.passphrase.separator_category.as_ref()?)
+    }
+
+    pub fn get_word_count(&self) -> usize {
+        self.target_entropy
+            .and_then(|bits| self.get_word_count_for_entropy(bits))
+            .unwrap_or(self.passphrase.word_count)
+    }
+
+    pub fn set_word_count(&mut self, count: usize) {
+        self.passphrase.word_count = count;
+        self.target_entropy = None;
+    }
+
+    // Every word, separator, capitalization and digit is drawn independently and uniformly, so
+    // the entropy is the log2 of the number of outcomes. Words containing a hyphen can make a few
+    // of them collide with a "-" separator, which is ignored here.
+    pub fn get_passphrase_entropy_bits(&self, word_count: usize) -> f64 {
+        if word_count == 0 {
+            return 0.0;
+        }
+        let options = &self.passphrase;
+        let mut bits = word_count as f64 * (options.wordlist.get_words().len() as f64).log2();
+        if let Some(cat) = self.get_separator_category() {
+            bits += (word_count - 1) as f64 * (cat.get_active_chars().len() as f64).log2();
+        }
+        if options.capitalization == Capitalization::Random {
+            bits += word_count as f64;
+        }
+        if options.digits > 0 {
+            bits += options.digits as f64 * (DIGITS.len() as f64).log2();
+            bits += (word_count as f64).log2();
+        }
+        bits
+    }
+
+    pub fn get_word_count_for_entropy(&self, bits: f64) -> Option<usize> {
+        (1..=MAX_DERIVED_WORD_COUNT)
+            .find(|count| reaches_entropy(self.get_passphrase_entropy_bits(*count), bits))
+    }
+
+    pub fn generate_passphrase(&self, wordlist: &[&str], rng: &mut ChaCha20Rng) -> String {
+        let options = &self.passphrase;
+        let word_count = self.get_word_count();
+
+        let mut words: Vec<String> = generate_random_words_from_word_vec(wordlist, word_count, rng)
+            .into_iter()
+            .map(|word| match options.capitalization {
+                Capitalization::Lowercase => word.to_string(),
+                Capitalization::Words => capitalize(word),
+                Capitalization::Random => match rng.random_bool(0.5) {
+                    true => capitalize(word),
+                    false => word.to_string(),
+                },
+                Capitalization::Uppercase => word.to_uppercase(),
+            })
+            .collect();
+
+        if options.digits > 0 {
+            let position = rng.random_range(0..word_count);
+            words[position].extend(generate_random_chars_from_char_vec(
+                &DIGITS.chars().collect::<Vec<char>>(),
+                options.digits,
+                rng,
+            ));
+        }
+
+        let separators: Vec<String> = match self.get_separator_category() {
+            Some(cat) => generate_random_chars_from_char_vec(
+                &cat.get_active_chars(),
+                word_count.saturating_sub(1),
+                rng,
+            )
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            None => vec![options.separator.clone(); word_count.saturating_sub(1)],
+        };
+
+        let mut result = String::new();
+        for (i, word) in words.iter().enumerate() {
+            if i > 0 {
+                result.push_str(&separators[i - 1]);
+            }
+            result.push_str(word);
+        }
+        result
+    }
+}
@@ -0,0 +1,607 @@
+use std::{collections::BTreeSet, fmt};
+
+use crate::app::config::{Config, GenerationMode};
+
+// The character classes of the `passwordrules` syntax. `special` is taken to be every printable
+// ASCII character that is not a letter or a digit, space included.
+const NAMED_CLASSES: &[&str] = &["upper", "lower", "digit", "special"];
+
+fn get_named_class(name: &str) -> Option<CharClass> {
+    let chars = (' '..='~').filter(|c| match name {
+        "upper" => c.is_ascii_uppercase(),
+        "lower" => c.is_ascii_lowercase(),
+        "digit" => c.is_ascii_digit(),
+        "special" => !c.is_ascii_alphanumeric(),
+        "ascii-printable" => true,
+        _ => false,
+    });
+    match name {
+        "unicode" => Some(CharClass {
+            unicode: true,
+            chars: BTreeSet::new(),
+        }),
+        "upper" | "lower" | "digit" | "special" | "ascii-printable" => Some(CharClass {
+            unicode: false,
+            chars: chars.collect(),
+        }),
+        _ => None,
+    }
+}
+
+// A set of characters, or every character when `unicode` is set.
+#[derive(Clone, Default, PartialEq)]
+pub struct CharClass {
+    pub unicode: bool,
+    pub chars: BTreeSet<char>,
+}
+
+impl CharClass {
+    fn contains(&self, c: char) -> bool {
+        self.unicode || self.chars.contains(&c)
+    }
+
+    fn extend(&mut self, other: &CharClass) {
+        self.unicode |= other.unicode;
+        self.chars.extend(&other.chars);
+    }
+}
+
+#[derive(Default)]
+pub struct PasswordRules {
+    pub required: Vec<CharClass>,
+    pub allowed: Option<CharClass>,
+    pub minlength: Option<usize>,
+    pub maxlength: Option<usize>,
+    pub max_consecutive: Option<usize>,
+    // Rule names this parser does not know, which browsers ignore as well.
+    pub ignored: Vec<String>,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum PasswordRulesError {
+    MissingColon(usize),
+    UnknownClass(String),
+    UnclosedClass(usize),
+    UnexpectedCharacter(usize),
+    InvalidNumber { rule: String, value: String },
+    LengthBounds { minlength: usize, maxlength: usize },
+    UnsupportedMode(String),
+}
+
+impl fmt::Display for PasswordRulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordRulesError::MissingColon(position) => write!(
+                f,
+                "The rule at position {} of the password rules is missing a \":\".",
+                position
+            ),
+            PasswordRulesError::UnknownClass(name) => write!(
+                f,
+                "The password rules refer to an unknown character class \"{}\".",
+                name
+            ),
+            PasswordRulesError::UnclosedClass(position) => write!(
+                f,
+                "The character class opened at position {} of the password rules is never closed.",
+                position
+            ),
+            PasswordRulesError::UnexpectedCharacter(position) => write!(
+                f,
+                "The password rules have an unexpected character at position {}.",
+                position
+            ),
+            PasswordRulesError::InvalidNumber { rule, value } => write!(
+                f,
+                "The \"{}\" rule has an invalid value \"{}\".",
+                rule, value
+            ),
+            PasswordRulesError::LengthBounds {
+                minlength,
+                maxlength,
+            } => write!(
+                f,
+                "The password rules ask for a minimum length of {} but a maximum length of {}.",
+                minlength, maxlength
+            ),
+            PasswordRulesError::UnsupportedMode(mode) => write!(
+                f,
+                "Password rules can only describe the Characters generation mode, not {}.",
+                mode
+            ),
+        }
+    }
+}
+
+// Rules are usually copied out of an HTML attribute, so the entities that can appear there are
+// decoded first.
+fn decode_entities(rules: &str) -> String {
+    rules
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> String {
+        let start = self.position;
+        while self.peek().is_some_and(&f) {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+
+    // After a value the rule has to end, either with a ";" or with the end of the rules.
+    fn end_rule(&mut self) -> Result<(), PasswordRulesError> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Ok(()),
+            Some(';') => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(_) => Err(PasswordRulesError::UnexpectedCharacter(self.position + 1)),
+        }
+    }
+
+    // A "]" only closes a custom class when the class list or the rule ends after it, so "]" can
+    // itself be listed as the last character of the class.
+    fn closes_class(&self) -> bool {
+        let mut i = self.position + 1;
+        while self.chars.get(i).is_some_and(|c| c.is_whitespace()) {
+            i += 1;
+        }
+        matches!(self.chars.get(i), None | Some(',') | Some(';'))
+    }
+
+    fn parse_class_list(&mut self) -> Result<CharClass, PasswordRulesError> {
+        let mut class = CharClass::default();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('[') => {
+                    let start = self.position;
+                    self.position += 1;
+                    loop {
+                        match self.peek() {
+                            Some(']') if self.closes_class() => break,
+                            Some(c) => {
+                                class.chars.insert(c);
+                                self.position += 1;
+                            }
+                            None => return Err(PasswordRulesError::UnclosedClass(start + 1)),
+                        }
+                    }
+                    self.position += 1;
+                }
+                _ => {
+                    let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '-');
+                    let named = get_named_class(&name.to_ascii_lowercase())
+                        .ok_or(PasswordRulesError::UnknownClass(name))?;
+                    class.extend(&named);
+                }
+            }
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                _ => return Ok(class),
+            }
+        }
+    }
+
+    fn parse_number(&mut self, rule: &str) -> Result<usize, PasswordRulesError> {
+        self.skip_whitespace();
+        let value = self.take_while(|c| c != ';' && !c.is_whitespace());
+        value
+            .parse()
+            .map_err(|_| PasswordRulesError::InvalidNumber {
+                rule: rule.to_string(),
+                value,
+            })
+    }
+}
+
+// Follows the browsers in combining repeated rules: the longest minimum length, the shortest
+// maximum length and the strictest limit on repeated characters win, and allowed classes add up.
+pub fn parse_password_rules(rules: &str) -> Result<PasswordRules, PasswordRulesError> {
+    let mut parser = Parser {
+        chars: decode_entities(rules).chars().collect(),
+        position: 0,
+    };
+    let mut result = PasswordRules::default();
+
+    loop {
+        parser.skip_whitespace();
+        if parser.peek().is_none() {
+            break;
+        }
+        if parser.peek() == Some(';') {
+            parser.position += 1;
+            continue;
+        }
+
+        let start = parser.position;
+        let name = parser
+            .take_while(|c| c.is_ascii_alphanumeric() || c == '-')
+            .to_ascii_lowercase();
+        parser.skip_whitespace();
+        if parser.peek() != Some(':') {
+            return Err(PasswordRulesError::MissingColon(start + 1));
+        }
+        parser.position += 1;
+
+        match name.as_str() {
+            "required" => {
+                let class = parser.parse_class_list()?;
+                result.required.push(class);
+            }
+            "allowed" => {
+                let class = parser.parse_class_list()?;
+                result
+                    .allowed
+                    .get_or_insert_with(CharClass::default)
+                    .extend(&class);
+            }
+            "minlength" => {
+                let value = parser.parse_number(&name)?;
+                result.minlength = Some(result.minlength.map_or(value, |v| v.max(value)));
+            }
+            "maxlength" => {
+                let value = parser.parse_number(&name)?;
+                result.maxlength = Some(result.maxlength.map_or(value, |v| v.min(value)));
+            }
+            "max-consecutive" => {
+                let value = parser.parse_number(&name)?;
+                result.max_consecutive =
+                    Some(result.max_consecutive.map_or(value, |v| v.min(value)));
+            }
+            _ => {
+                parser.take_while(|c| c != ';');
+                result.ignored.push(name);
+            }
+        }
+        parser.end_rule()?;
+    }
+
+    if let (Some(minlength), Some(maxlength)) = (result.minlength, result.maxlength)
+        && minlength > maxlength
+    {
+        return Err(PasswordRulesError::LengthBounds {
+            minlength,
+            maxlength,
+        });
+    }
+    Ok(result)
+}
+
+// Named classes are used where the whole class is present, and anything left over goes into a
+// custom class with "-" first and "]" last, as the syntax requires.
+fn render_class(chars: &BTreeSet<char>) -> String {
+    if chars.iter().any(|c| !(' '..='~').contains(c)) {
+        return "unicode".to_string();
+    }
+    if get_named_class("ascii-printable").is_some_and(|class| class.chars == *chars) {
+        return "ascii-printable".to_string();
+    }
+
+    let mut rest = chars.clone();
+    let mut classes = Vec::new();
+    for name in NAMED_CLASSES {
+        let named = get_named_class(name)
+            .map(|class| class.chars)
+            .unwrap_or_default();
+        if named.is_subset(&rest) {
+            rest.retain(|c| !named.contains(c));
+            classes.push(name.to_string());
+        }
+    }
+    if !rest.is_empty() {
+        let mut custom = String::from("[");
+        if rest.contains(&'-') {
+            custom.push('-');
+        }
+        for c in rest.iter().filter(|c| !matches!(c, '-' | ']')) {
+            match c {
+                '"' => custom.push_str("&quot;"),
+                c => custom.push(*c),
+            }
+        }
+        if rest.contains(&']') {
+            custom.push(']');
+        }
+        custom.push(']');
+        classes.push(custom);
+    }
+    classes.join(", ")
+}
+
+impl Config {
+    // Replaces the current settings with ones that follow the rules. Returns a note for every
+    // part of the rules that can only be followed approximately.
+    pub fn apply_password_rules(&mut self, rules: &str) -> Result<Vec<String>, PasswordRulesError> {
+        let rules = parse_password_rules(rules)?;
+        let mut warnings: Vec<String> = rules
+            .ignored
+            .iter()
+            .map(|name| format!("Ignored the unknown rule \"{}\".", name))
+            .collect();
+
+        self.reset_to_defaults();
+        self.generation_mode = GenerationMode::Characters;
+
+        // Required characters are implicitly allowed, and without any class rule the syntax
+        // allows all printable ASCII characters.
+        let mut allowed = match (&rules.allowed, rules.required.is_empty()) {
+            (None, true) => get_named_class("ascii-printable").unwrap_or_default(),
+            (allowed, _) => allowed.clone().unwrap_or_default(),
+        };
+        for class in &rules.required {
+            allowed.extend(class);
+        }
+
+        for cat in self.categories.iter_mut() {
+            for cpc in cat.code_points.iter_mut() {
+                cpc.set_enabled(allowed.contains(cpc.get_char()));
+            }
+            cat.set_enabled(cat.code_points.iter().any(|cpc| cpc.is_enabled()));
+            cat.set_min_required_chars(0);
+            cat.set_max_allowed_chars(None);
+        }
+
+        // A required class is met by asking for one character of the largest category that fits
+        // entirely inside it. When that category does not cover the whole class, the generated
+        // passwords draw the required character from a smaller set than the rules would allow.
+        for (i, class) in rules.required.iter().enumerate() {
+            let best = self
+                .categories
+                .iter_mut()
+                .filter(|cat| cat.is_enabled())
+                .map(|cat| {
+                    let chars = cat.get_active_chars();
+                    (cat, chars)
+                })
+                .filter(|(_, chars)| chars.iter().all(|c| class.contains(*c)))
+                .max_by_key(|(_, chars)| chars.len());
+            match best {
+                Some((cat, chars)) => {
+                    let covered = class.unicode
+                        || class
+                            .chars
+                            .iter()
+                            .filter(|c| allowed.contains(**c))
+                            .all(|c| chars.contains(c));
+                    if !covered {
+                        warnings.push(format!(
+                            "Required class {} is approximated by \"{}\".",
+                            i + 1,
+                            cat.get_name()
+                        ));
+                    }
+                    cat.set_min_required_chars(1);
+                }
+                None => warnings.push(format!(
+                    "Required class {} is not covered by any category and cannot be guaranteed.",
+                    i + 1
+                )),
+            }
+        }
+
+        // A minimum without a maximum is taken as the length, since sites that publish one often
+        // reject or cut off passwords far longer than it. Otherwise the default length is kept
+        // within the bounds.
+        let length = match (rules.minlength, rules.maxlength) {
+            (Some(minlength), None) => minlength,
+            (minlength, maxlength) => self
+                .password_length
+                .max(minlength.unwrap_or(0))
+                .min(maxlength.unwrap_or(usize::MAX)),
+        };
+        self.set_password_length(length);
+        self.set_max_consecutive(rules.max_consecutive);
+
+        Ok(warnings)
+    }
+
+    // The password length is exported as both bounds, since every password this configuration
+    // produces has exactly that length. Lengths in other units than code points are exported as
+    // the range of code point counts they can take.
+    pub fn to_password_rules(&self) -> Result<String, PasswordRulesError> {
+        if self.generation_mode != GenerationMode::Characters {
+            return Err(PasswordRulesError::UnsupportedMode(
+                self.generation_mode.get_label().to_string(),
+            ));
+        }
+
+        let length = self.get_password_length();
+        let counts: Vec<usize> = match self.length_unit.counts_code_points() {
+            true => vec![length],
+            false => self
+                .get_char_counts(length)
+                .iter()
+                .map(|count| count.count)
+                .collect(),
+        };
+        let mut rules = vec![
+            format!("minlength: {}", counts.first().copied().unwrap_or(length)),
+            format!("maxlength: {}", counts.last().copied().unwrap_or(length)),
+        ];
+        let mut allowed = BTreeSet::new();
+        for cat in self.get_active_categories() {
+            let chars: BTreeSet<char> = cat.get_active_chars().into_iter().collect();
+            if cat.get_min_required_chars() > 0 {
+                rules.push(format!("required: {}", render_class(&chars)));
+            }
+            allowed.extend(chars);
+        }
+        rules.push(format!("allowed: {}", render_class(&allowed)));
+        if let Some(max) = self.get_max_consecutive() {
+            rules.push(format!("max-consecutive: {}", max));
+        }
+        Ok(rules.join("; ") + ";")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{PasswordRulesError, get_named_class, parse_password_rules, render_class};
+    use crate::app::config::Config;
+
+    fn chars(s: &str) -> BTreeSet<char> {
+        s.chars().collect()
+    }
+
+    fn named(name: &str) -> BTreeSet<char> {
+        get_named_class(name).unwrap().chars
+    }
+
+    fn allowed(rules: &str) -> BTreeSet<char> {
+        parse_password_rules(rules)
+            .ok()
+            .unwrap()
+            .allowed
+            .unwrap()
+            .chars
+    }
+
+    #[test]
+    fn brackets_and_dashes_are_literal_inside_classes() {
+        assert_eq!(allowed("allowed: [-]]"), chars("-]"));
+        assert_eq!(allowed("allowed: [a]b]"), chars("a]b"));
+        assert_eq!(allowed("allowed: [-a-z]"), chars("-az"));
+        assert_eq!(
+            allowed("allowed: [a]], digit"),
+            &chars("a]") | &named("digit")
+        );
+        assert!(matches!(
+            parse_password_rules("allowed: [ab"),
+            Err(PasswordRulesError::UnclosedClass(10))
+        ));
+    }
+
+    #[test]
+    fn html_entities_are_decoded() {
+        assert_eq!(
+            allowed("allowed: [&quot;&amp;&lt;&gt;&apos;]"),
+            chars("\"&<>'")
+        );
+        let rules = parse_password_rules("minlength: 8&#59; maxlength: 9").err();
+        assert!(matches!(
+            rules,
+            Some(PasswordRulesError::InvalidNumber { .. })
+        ));
+    }
+
+    #[test]
+    fn unknown_rules_are_ignored_but_unknown_classes_are_not() {
+        let rules = parse_password_rules("passwordrules-v2: whatever; MinLength: 8")
+            .ok()
+            .unwrap();
+        assert_eq!(rules.ignored, vec!["passwordrules-v2".to_string()]);
+        assert_eq!(rules.minlength, Some(8));
+        assert!(matches!(
+            parse_password_rules("required: vowels").err(),
+            Some(PasswordRulesError::UnknownClass(name)) if name == "vowels"
+        ));
+        assert!(matches!(
+            parse_password_rules("minlength 8").err(),
+            Some(PasswordRulesError::MissingColon(1))
+        ));
+    }
+
+    #[test]
+    fn the_strictest_max_consecutive_wins() {
+        let rules =
+            parse_password_rules("max-consecutive: 3; max-consecutive: 2; max-consecutive: 4")
+                .ok()
+                .unwrap();
+        assert_eq!(rules.max_consecutive, Some(2));
+        assert!(matches!(
+            parse_password_rules("max-consecutive: two").err(),
+            Some(PasswordRulesError::InvalidNumber { rule, value }) if rule == "max-consecutive" && value == "two"
+        ));
+    }
+
+    #[test]
+    fn rendered_classes_parse_back_to_the_same_characters() {
+        let sets = [
+            named("ascii-printable"),
+            named("digit"),
+            &named("upper") | &named("lower"),
+            &named("digit") | &chars("-]"),
+            chars("-"),
+            chars("]"),
+            chars("\"&<>;,"),
+            &named("special") | &chars("xyz"),
+        ];
+        for set in sets {
+            let rendered = render_class(&set);
+            assert_eq!(
+                allowed(&format!("allowed: {}", rendered)),
+                set,
+                "{}",
+                rendered
+            );
+        }
+        assert_eq!(render_class(&chars("-a]")), "[-a]]");
+        assert_eq!(render_class(&chars("é")), "unicode");
+    }
+
+    #[test]
+    fn exported_rules_parse_back_to_the_imported_ones() {
+        let original = "required: upper; required: digit; allowed: lower, [-_]; minlength: 12; maxlength: 12; max-consecutive: 2";
+        let mut config = Config::new();
+        config.apply_password_rules(original).ok().unwrap();
+        let exported = config.to_password_rules().ok().unwrap();
+
+        let before = parse_password_rules(original).ok().unwrap();
+        let after = parse_password_rules(&exported).ok().unwrap();
+        assert_eq!(after.minlength, before.minlength);
+        assert_eq!(after.maxlength, before.maxlength);
+        assert_eq!(after.max_consecutive, before.max_consecutive);
+        assert_eq!(
+            after.allowed.unwrap().chars,
+            &named("upper") | &(&named("digit") | &(&named("lower") | &chars("-_")))
+        );
+        let required: Vec<BTreeSet<char>> = after.required.into_iter().map(|c| c.chars).collect();
+        assert_eq!(required, vec![named("upper"), named("digit")]);
+
+        // Exporting the imported export changes nothing.
+        let mut reimported = Config::new();
+        reimported.apply_password_rules(&exported).ok().unwrap();
+        assert_eq!(reimported.to_password_rules().ok().unwrap(), exported);
+    }
+
+    #[test]
+    fn a_lone_minlength_sets_the_length() {
+        for (rules, length) in [
+            ("minlength: 20; required: lower", 20),
+            ("minlength: 200", 200),
+            ("maxlength: 12", 12),
+            ("minlength: 8; maxlength: 64", 64),
+            ("required: digit", 128),
+        ] {
+            let mut config = Config::new();
+            config.apply_password_rules(rules).ok().unwrap();
+            assert_eq!(config.get_password_length(), length, "{}", rules);
+        }
+    }
+}
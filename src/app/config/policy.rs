@@ -0,0 +1,406 @@
+use std::fmt;
+
+use crate::app::config::{
+    Config, GenerationMode, SamplingMode,
+    category::CodePointCategory,
+    entropy::reaches_entropy,
+    regex::{MAX_REGEX_LENGTH, RegexError},
+    template::TemplateError,
+};
+
+#[derive(Clone, PartialEq)]
+pub enum PolicyError {
+    NoActiveCategories,
+    CategoryWithoutCodePoints(String),
+    MinExceedsMax {
+        category: String,
+        min: usize,
+        max: usize,
+    },
+    OverlappingLimits {
+        first: String,
+        second: String,
+    },
+    LengthTooShort {
+        length: usize,
+        minimum: usize,
+    },
+    LengthTooLong {
+        length: usize,
+        maximum: usize,
+    },
+    NoPositiveWeight,
+    EntropyUnreachable(f64),
+    NoWords,
+    SeparatorCategoryUnavailable(String),
+    NoSyllables,
+    ExtraCategoryUnavailable(String),
+    Template(TemplateError),
+    Regex(RegexError),
+    RegexLengthTooLong {
+        length: usize,
+        maximum: usize,
+    },
+    RegexNoMatch {
+        length: usize,
+        nearest: Option<usize>,
+    },
+    RulesRejectEverything,
+    EndsUnfillable,
+    NoScriptFits(&'static str),
+    LengthUnreachable {
+        length: usize,
+        unit: &'static str,
+    },
+    LengthUnitUnsupported(&'static str),
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyError::NoActiveCategories => write!(
+                f,
+                "No category is enabled with at least one enabled code point, so there is nothing to generate from."
+            ),
+            PolicyError::CategoryWithoutCodePoints(name) => write!(
+                f,
+                "\"{}\" is enabled but none of its code points are. Enable some of its code points or disable the category.",
+                name
+            ),
+            PolicyError::MinExceedsMax { category, min, max } => write!(
+                f,
+                "\"{}\" requires at least {} characters but allows at most {}.",
+                category, min, max
+            ),
+            PolicyError::OverlappingLimits { first, second } => write!(
+                f,
+                "\"{}\" and \"{}\" share characters, so the characters of one could count towards the minimum or maximum of the other. Remove the minimums and maximums of both or disable one of them.",
+                first, second
+            ),
+            PolicyError::LengthTooShort { length, minimum } => write!(
+                f,
+                "The password length of {} is shorter than the {} characters required by the minimums of the enabled categories.",
+                length, minimum
+            ),
+            PolicyError::LengthTooLong { length, maximum } => write!(
+                f,
+                "The password length of {} is longer than the {} characters the enabled categories may contribute under their maximums.",
+                length, maximum
+            ),
+            PolicyError::NoPositiveWeight => write!(
+                f,
+                "Weighted sampling needs at least one enabled category with a positive weight to fill the characters beyond the minimums."
+            ),
+            PolicyError::EntropyUnreachable(bits) => write!(
+                f,
+                "The current settings cannot reach the target entropy of {} bits.",
+                bits
+            ),
+            PolicyError::NoWords => write!(f, "A passphrase needs at least one word."),
+            PolicyError::NoSyllables => {
+                write!(f, "A pronounceable password needs at least one syllable.")
+            }
+            PolicyError::ExtraCategoryUnavailable(name) => write!(
+                f,
+                "Extra characters are drawn from \"{}\", which is not an enabled category with enabled code points.",
+                name
+            ),
+            PolicyError::SeparatorCategoryUnavailable(name) => write!(
+                f,
+                "Separators are drawn from \"{}\", which is not an enabled category with enabled code points.",
+                name
+            ),
+            PolicyError::Template(e) => write!(f, "{}", e),
+            PolicyError::RulesRejectEverything => write!(
+                f,
+                "The rejection rules turn down practically every password the current settings produce."
+            ),
+            PolicyError::EndsUnfillable => write!(
+                f,
+                "No enabled characters fit the first and last position rules while leaving the rest of the password within the category minimums and maximums."
+            ),
+            PolicyError::NoScriptFits(level) => write!(
+                f,
+                "No script, or combination of scripts allowed at the {} restriction level, covers the enabled characters while meeting the category minimums and maximums.",
+                level
+            ),
+            PolicyError::LengthUnreachable { length, unit } => write!(
+                f,
+                "No password of exactly {} {} can be built from the enabled characters within the category minimums and maximums.",
+                length, unit
+            ),
+            PolicyError::LengthUnitUnsupported(unit) => write!(
+                f,
+                "Regex mode counts the password length in code points, not {}.",
+                unit
+            ),
+            PolicyError::Regex(e) => write!(f, "{}", e),
+            PolicyError::RegexLengthTooLong { length, maximum } => write!(
+                f,
+                "The password length of {} is longer than the {} characters supported in regex mode.",
+                length, maximum
+            ),
+            PolicyError::RegexNoMatch {
+                length,
+                nearest: Some(nearest),
+            } => write!(
+                f,
+                "No string of {} characters from the enabled code points matches the regex. The closest length that does is {}.",
+                length, nearest
+            ),
+            PolicyError::RegexNoMatch {
+                length,
+                nearest: None,
+            } => write!(
+                f,
+                "No string of {} characters from the enabled code points matches the regex, nor does any string of up to {} characters.",
+                length, MAX_REGEX_LENGTH
+            ),
+        }
+    }
+}
+
+impl Config {
+    // Checks that `generate_password` can produce a password with the current settings, and
+    // reports every reason it cannot.
+    pub fn validate_policy(&self) -> Result<(), Vec<PolicyError>> {
+        match self.generation_mode {
+            GenerationMode::Characters => self.validate_character_policy(),
+            GenerationMode::Passphrase => self.validate_passphrase_policy(),
+            GenerationMode::Pronounceable => self.validate_pronounceable_policy(),
+            GenerationMode::Template => self.validate_template_policy(),
+            GenerationMode::Regex => self.validate_regex_policy(),
+        }?;
+
+        // Only checked once the mode itself is sound, since it draws sample passwords.
+        match self.get_acceptance_rate() > 0.0 {
+            true => Ok(()),
+            false => Err(vec![PolicyError::RulesRejectEverything]),
+        }
+    }
+
+    fn validate_passphrase_policy(&self) -> Result<(), Vec<PolicyError>> {
+        let mut errors = Vec::new();
+
+        if let Some(name) = self
+            .passphrase
+            .separator_category
+            .as_ref()
+            .filter(|_| self.get_separator_category().is_none())
+        {
+            errors.push(PolicyError::SeparatorCategoryUnavailable(name.clone()));
+        }
+
+        match self.target_entropy {
+            Some(bits) if self.get_word_count_for_entropy(bits).is_none() => {
+                errors.push(PolicyError::EntropyUnreachable(bits))
+            }
+            _ if self.get_word_count() == 0 => errors.push(PolicyError::NoWords),
+            _ => {}
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    fn validate_pronounceable_policy(&self) -> Result<(), Vec<PolicyError>> {
+        let mut errors: Vec<PolicyError> = self
+            .pronounceable
+            .extra_characters
+            .iter()
+            .filter(|extra| extra.count > 0)
+            .filter(|extra| self.get_active_category_by_name(&extra.category).is_none())
+            .map(|extra| PolicyError::ExtraCategoryUnavailable(extra.category.clone()))
+            .collect();
+
+        match self.target_entropy {
+            Some(bits) if self.get_syllable_count_for_entropy(bits).is_none() => {
+                errors.push(PolicyError::EntropyUnreachable(bits))
+            }
+            _ if self.get_syllable_count() == 0 => errors.push(PolicyError::NoSyllables),
+            _ => {}
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    // A template fixes the length, so a target entropy can only be checked, not reached.
+    fn validate_template_policy(&self) -> Result<(), Vec<PolicyError>> {
+        if let Err(e) = self.validate_template() {
+            return Err(vec![PolicyError::Template(e)]);
+        }
+        match self.target_entropy {
+            Some(bits) if !reaches_entropy(self.get_template_entropy_bits(), bits) => {
+                Err(vec![PolicyError::EntropyUnreachable(bits)])
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_regex_policy(&self) -> Result<(), Vec<PolicyError>> {
+        if !self.length_unit.counts_code_points() {
+            return Err(vec![PolicyError::LengthUnitUnsupported(
+                self.length_unit.get_label(),
+            )]);
+        }
+        let automaton = self
+            .get_regex_automaton()
+            .map_err(|e| vec![PolicyError::Regex(e)])?;
+        if let Some(bits) = self.target_entropy {
+            return match automaton.get_length_for_entropy(bits) {
+                Some(_) => Ok(()),
+                None => Err(vec![PolicyError::EntropyUnreachable(bits)]),
+            };
+        }
+
+        let length = self.password_length;
+        if length > MAX_REGEX_LENGTH {
+            return Err(vec![PolicyError::RegexLengthTooLong {
+                length,
+                maximum: MAX_REGEX_LENGTH,
+            }]);
+        }
+        match automaton.get_log2_count(length) {
+            Some(_) => Ok(()),
+            None => Err(vec![PolicyError::RegexNoMatch {
+                length,
+                nearest: automaton.get_nearest_length(length),
+            }]),
+        }
+    }
+
+    fn validate_character_policy(&self) -> Result<(), Vec<PolicyError>> {
+        let mut errors = Vec::new();
+
+        for cat in self.categories.iter().filter(|cat| cat.is_enabled()) {
+            if cat.get_active_code_point_count() == 0 {
+                errors.push(PolicyError::CategoryWithoutCodePoints(
+                    cat.get_name().to_string(),
+                ));
+            }
+            if let Some(max) = cat
+                .get_max_allowed_chars()
+                .filter(|max| *max < cat.get_min_required_chars())
+            {
+                errors.push(PolicyError::MinExceedsMax {
+                    category: cat.get_name().to_string(),
+                    min: cat.get_min_required_chars(),
+                    max,
+                });
+            }
+        }
+
+        if !self.has_active_categories() {
+            errors.push(PolicyError::NoActiveCategories);
+            return Err(errors);
+        }
+
+        for (first, second) in self.get_overlapping_categories() {
+            let limited = |cat: &CodePointCategory| {
+                cat.get_min_required_chars() > 0 || cat.get_max_allowed_chars().is_some()
+            };
+            if limited(first) || limited(second) {
+                errors.push(PolicyError::OverlappingLimits {
+                    first: first.get_name().to_string(),
+                    second: second.get_name().to_string(),
+                });
+            }
+        }
+
+        if let Some(bits) = self
+            .target_entropy
+            .filter(|bits| self.get_length_for_entropy(*bits).is_none())
+        {
+            // Without a length for the target, the checks below would report on the fixed length
+            // the target replaces.
+            errors.push(PolicyError::EntropyUnreachable(bits));
+            return Err(errors);
+        }
+
+        // The minimums and maximums count code points, so they only bound the length directly when
+        // it is counted in code points too.
+        let length = self.get_password_length();
+        let minimum = self.get_min_required_chars_for_active_categories();
+        let unit = self.length_unit;
+        if unit.counts_code_points() && length < minimum {
+            errors.push(PolicyError::LengthTooShort { length, minimum });
+        }
+
+        if let Some(maximum) = self
+            .get_max_total_chars_for_active_categories()
+            .filter(|maximum| unit.counts_code_points() && *maximum < length)
+        {
+            errors.push(PolicyError::LengthTooLong { length, maximum });
+        }
+
+        if self.sampling_mode == SamplingMode::Weighted
+            && !self.has_positive_weight_for_active_categories()
+            && length > minimum
+        {
+            errors.push(PolicyError::NoPositiveWeight);
+        }
+
+        if errors.is_empty() {
+            match unit.counts_code_points() {
+                true => {
+                    let pools = self.get_entropy_pools(&self.get_character_pools());
+                    if self.places_ends() && self.get_placed_entropy_bits(&pools, length).is_none()
+                    {
+                        errors.push(PolicyError::EndsUnfillable);
+                    } else if self
+                        .get_restricted_pool_set_probabilities(length)
+                        .iter()
+                        .all(|p| *p == 0.0)
+                    {
+                        errors.push(PolicyError::NoScriptFits(
+                            self.restriction_level.get_label(),
+                        ));
+                    }
+                }
+                false => {
+                    if self.get_char_counts(length).is_empty() {
+                        errors.push(PolicyError::LengthUnreachable {
+                            length,
+                            unit: unit.get_label(),
+                        });
+                    }
+                }
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    // The largest minimum the category at `cat_idx` can take before the minimums of the enabled
+    // categories no longer fit in the password length or the category's own maximum.
+    pub fn get_max_min_chars_for_category(&self, cat_idx: usize) -> usize {
+        let cat = &self.categories[cat_idx];
+        let length_bound = match self.target_entropy {
+            Some(_) => usize::MAX,
+            None => {
+                let others: usize = self
+                    .categories
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, other)| {
+                        *i != cat_idx
+                            && other.is_enabled()
+                            && other.get_active_code_point_count() > 0
+                    })
+                    .map(|(_, other)| other.get_min_required_chars())
+                    .sum();
+                self.password_length.saturating_sub(others)
+            }
+        };
+        cat.get_max_allowed_chars()
+            .map_or(length_bound, |max| max.min(length_bound))
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::app::config::{Config, SamplingMode, category::CodePointCategory};
+
+// The enabled characters, split into disjoint pools. Each category becomes its own pool unless it
+// shares characters with another category, in which case the two are merged and their weights are
+// added up. A merged pool cannot tell which category a character counts for, so validation only
+// lets categories without a minimum or maximum share characters.
+pub struct CharacterPool {
+    pub chars: Vec<char>,
+    pub min: usize,
+    pub max: Option<usize>,
+    pub weight: u64,
+}
+
+impl CharacterPool {
+    // The number of characters the pool can contribute beyond its minimum, if limited.
+    pub fn get_extra_cap(&self) -> Option<usize> {
+        self.max.map(|max| max.saturating_sub(self.min))
+    }
+}
+
+impl Config {
+    pub fn get_character_pools(&self) -> Vec<CharacterPool> {
+        let mut pools: Vec<(HashSet<char>, CharacterPool)> = Vec::new();
+        for cat in self.get_active_categories() {
+            let chars = cat.get_active_chars();
+            let mut set: HashSet<char> = chars.iter().copied().collect();
+            let mut pool = CharacterPool {
+                chars,
+                min: cat.get_min_required_chars(),
+                max: cat.get_max_allowed_chars(),
+                weight: cat.get_weight().into(),
+            };
+            let (overlapping, disjoint): (Vec<_>, Vec<_>) = pools
+                .into_iter()
+                .partition(|(other, _)| !other.is_disjoint(&set));
+            for (other_set, other) in overlapping {
+                pool.chars
+                    .extend(other.chars.into_iter().filter(|c| !set.contains(c)));
+                set.extend(other_set);
+                pool.min += other.min;
+                pool.max = pool.max.zip(other.max).map(|(a, b)| a + b);
+                pool.weight += other.weight;
+            }
+            pools = disjoint;
+            pools.push((set, pool));
+        }
+        pools.into_iter().map(|(_, pool)| pool).collect()
+    }
+
+    // Every pair of enabled categories that share at least one character.
+    pub fn get_overlapping_categories(&self) -> Vec<(&CodePointCategory, &CodePointCategory)> {
+        let categories = self.get_active_categories();
+        let mut owners: HashMap<char, Vec<usize>> = HashMap::new();
+        for (i, cat) in categories.iter().enumerate() {
+            for c in cat.get_active_chars() {
+                owners.entry(c).or_default().push(i);
+            }
+        }
+        let pairs: BTreeSet<(usize, usize)> = owners
+            .values()
+            .filter(|owners| owners.len() > 1)
+            .flat_map(|owners| {
+                owners
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(k, i)| owners[k + 1..].iter().map(|j| (*i, *j)))
+            })
+            .collect();
+        pairs
+            .into_iter()
+            .map(|(i, j)| (categories[i], categories[j]))
+            .collect()
+    }
+
+    pub fn pool_takes_extra_chars(&self, pool: &CharacterPool) -> bool {
+        self.takes_extra_chars(pool.weight)
+    }
+
+    // Whether a pool with `weight` supplies characters beyond its minimum.
+    pub fn takes_extra_chars(&self, weight: u64) -> bool {
+        self.sampling_mode == SamplingMode::Uniform || weight > 0
+    }
+
+    // The most characters the enabled categories can produce together, or `None` when at least
+    // one of them is unlimited. Categories with a zero weight in weighted mode never contribute
+    // beyond their minimum.
+    pub fn get_max_total_chars_for_active_categories(&self) -> Option<usize> {
+        self.get_active_categories()
+            .iter()
+            .map(
+                |cat| match self.sampling_mode == SamplingMode::Uniform || cat.get_weight() > 0 {
+                    true => cat.get_max_allowed_chars(),
+                    false => Some(cat.get_min_required_chars()),
+                },
+            )
+            .sum()
+    }
+}
@@ -0,0 +1,325 @@
+use rand::{
+    distr::{Distribution, weighted::WeightedIndex},
+    seq::IndexedRandom,
+};
+
+use rand_chacha::ChaCha20Rng;
+
+use crate::app::config::{
+    Config, GenerationMode,
+    category::{CodePointCategory, PositionRule},
+    entropy::EntropyPool,
+    pool::CharacterPool,
+};
+
+// The characters of one pool that may start the password, end it, or make up all of it.
+pub struct PoolEnds {
+    pub first: Vec<char>,
+    pub last: Vec<char>,
+    pub both: Vec<char>,
+}
+
+// A character fits a position when no category containing it forbids the position, and it
+// belongs to one of the categories that require the position, if there are any.
+fn fits(c: char, rules: &[(&CodePointCategory, PositionRule)]) -> bool {
+    let mut required = false;
+    let mut in_required = false;
+    for (cat, rule) in rules {
+        match rule {
+            PositionRule::Allowed => {}
+            PositionRule::Forbidden if cat.has_active_char(c) => return false,
+            PositionRule::Forbidden => {}
+            PositionRule::Required => {
+                required = true;
+                in_required |= cat.has_active_char(c);
+            }
+        }
+    }
+    !required || in_required
+}
+
+// `pools` once the ends have taken one character from each pool in `taken`. A character taken
+// from a pool counts towards its minimum while there is one, and always against its maximum.
+fn get_middle_pools(pools: &[EntropyPool], taken: &[usize]) -> Vec<EntropyPool> {
+    pools
+        .iter()
+        .enumerate()
+        .map(|(i, pool)| {
+            let count = taken.iter().filter(|k| **k == i).count();
+            EntropyPool {
+                min: pool.min.saturating_sub(count),
+                max: pool.max.map(|max| max.saturating_sub(count)),
+                ..*pool
+            }
+        })
+        .collect()
+}
+
+impl Config {
+    pub fn has_position_rules(&self) -> bool {
+        self.categories.iter().any(|cat| {
+            cat.is_enabled()
+                && (cat.get_first_rule() != PositionRule::Allowed
+                    || cat.get_last_rule() != PositionRule::Allowed)
+        })
+    }
+
+    // The enabled categories with a rule other than `Allowed` for a position.
+    fn get_position_rules(
+        &self,
+        rule: impl Fn(&CodePointCategory) -> PositionRule,
+    ) -> Vec<(&CodePointCategory, PositionRule)> {
+        self.categories
+            .iter()
+            .filter(|cat| cat.is_enabled())
+            .map(|cat| (cat, rule(cat)))
+            .filter(|(_, rule)| *rule != PositionRule::Allowed)
+            .collect()
+    }
+
+    pub fn fits_position(
+        &self,
+        c: char,
+        rule: impl Fn(&CodePointCategory) -> PositionRule,
+    ) -> bool {
+        fits(c, &self.get_position_rules(rule))
+    }
+
+    // Character passwords counted in code points get their first and last characters placed
+    // directly. Other modes, and other length units, whose ends depend on the widths of the
+    // characters, leave the position rules to rejection.
+    pub fn places_ends(&self) -> bool {
+        self.generation_mode == GenerationMode::Characters
+            && self.length_unit.counts_code_points()
+            && self.has_position_rules()
+    }
+
+    pub fn get_pool_ends(&self, pool: &CharacterPool) -> PoolEnds {
+        let first_rules = self.get_position_rules(CodePointCategory::get_first_rule);
+        let last_rules = self.get_position_rules(CodePointCategory::get_last_rule);
+        let mut ends = PoolEnds {
+            first: Vec::new(),
+            last: Vec::new(),
+            both: Vec::new(),
+        };
+        for c in &pool.chars {
+            let first = fits(*c, &first_rules);
+            let last = fits(*c, &last_rules);
+            if first {
+                ends.first.push(*c);
+            }
+            if last {
+                ends.last.push(*c);
+            }
+            if first && last {
+                ends.both.push(*c);
+            }
+        }
+        ends
+    }
+
+    // The pools the first and last characters of a password of `length` code points come from,
+    // with the probability of each pair; a single character comes from the same pool twice.
+    // The first character is drawn uniformly from those that fit it, in either sampling mode,
+    // among the pools that leave the rest of the password possible, and then the last one.
+    pub fn get_end_pools(&self, pools: &[EntropyPool], length: usize) -> Vec<(usize, usize, f64)> {
+        let fits_middle = |taken: &[usize]| {
+            taken.iter().all(|i| {
+                let pool = &pools[*i];
+                let count = taken.iter().filter(|k| *k == i).count();
+                (count <= pool.min || self.takes_extra_chars(pool.weight))
+                    && pool.max.is_none_or(|max| count <= max)
+            }) && self.fits_pools(
+                &get_middle_pools(pools, taken),
+                length.saturating_sub(taken.len()),
+            )
+        };
+        if length == 1 {
+            let candidates: Vec<usize> = (0..pools.len())
+                .filter(|i| pools[*i].both > 0 && fits_middle(&[*i]))
+                .collect();
+            let total: usize = candidates.iter().map(|i| pools[*i].both).sum();
+            return candidates
+                .into_iter()
+                .map(|i| (i, i, pools[i].both as f64 / total as f64))
+                .collect();
+        }
+        if length == 0 {
+            return Vec::new();
+        }
+
+        let lasts: Vec<Vec<usize>> = (0..pools.len())
+            .map(|i| match pools[i].first {
+                0 => Vec::new(),
+                _ => (0..pools.len())
+                    .filter(|j| pools[*j].last > 0 && fits_middle(&[i, *j]))
+                    .collect(),
+            })
+            .collect();
+        let total: usize = (0..pools.len())
+            .filter(|i| !lasts[*i].is_empty())
+            .map(|i| pools[i].first)
+            .sum();
+        let mut pairs = Vec::new();
+        for (i, lasts) in lasts.iter().enumerate() {
+            let last_total: usize = lasts.iter().map(|j| pools[*j].last).sum();
+            for j in lasts {
+                let p = pools[i].first as f64 / total as f64;
+                pairs.push((i, *j, p * pools[*j].last as f64 / last_total as f64));
+            }
+        }
+        pairs
+    }
+
+    // The entropy of a password whose ends are placed by `get_end_pools` and whose middle is
+    // drawn from what the ends leave of the pools, or `None` when no pair of ends fits. The
+    // pools are disjoint, so each character reveals its pool and the entropy adds up over the
+    // choice of pools, the end characters and the middle.
+    pub fn get_placed_entropy_bits(&self, pools: &[EntropyPool], length: usize) -> Option<f64> {
+        if length == 0 {
+            return self
+                .fits_pools(pools, length)
+                .then(|| self.get_drawn_entropy_bits(pools, length));
+        }
+        let pairs = self.get_end_pools(pools, length);
+        if pairs.is_empty() {
+            return None;
+        }
+        let bits = pairs
+            .iter()
+            .map(|(i, j, p)| {
+                let (taken, ends) = match length {
+                    1 => (vec![*i], (pools[*i].both as f64).log2()),
+                    _ => (
+                        vec![*i, *j],
+                        (pools[*i].first as f64).log2() + (pools[*j].last as f64).log2(),
+                    ),
+                };
+                let middle = self
+                    .get_drawn_entropy_bits(&get_middle_pools(pools, &taken), length - taken.len());
+                p * (ends + middle - p.log2())
+            })
+            .sum();
+        Some(bits)
+    }
+
+    // Prepares drawing passwords of `length` code points with placed ends from `pools`.
+    pub fn get_placed_sampler(
+        &self,
+        mut pools: Vec<CharacterPool>,
+        length: usize,
+    ) -> Box<dyn FnMut(&mut ChaCha20Rng) -> String + '_> {
+        let ends: Vec<PoolEnds> = pools.iter().map(|pool| self.get_pool_ends(pool)).collect();
+        let pairs = self.get_end_pools(&self.get_entropy_pools(&pools), length);
+        let index = WeightedIndex::new(pairs.iter().map(|(_, _, p)| *p)).ok();
+        Box::new(move |rng| {
+            let Some(index) = &index else {
+                return String::new();
+            };
+            let (i, j, _) = pairs[index.sample(rng)];
+            let (first, last) = match length {
+                1 => (ends[i].both.choose(rng), None),
+                _ => (ends[i].first.choose(rng), ends[j].last.choose(rng)),
+            };
+            let taken = match length {
+                1 => vec![i],
+                _ => vec![i, j],
+            };
+
+            // The middle is drawn like any other password from the pools with the ends counted
+            // against them, and then put back.
+            let saved: Vec<(usize, Option<usize>)> = taken
+                .iter()
+                .map(|k| (pools[*k].min, pools[*k].max))
+                .collect();
+            for k in &taken {
+                let pool = &mut pools[*k];
+                pool.min = pool.min.saturating_sub(1);
+                pool.max = pool.max.map(|max| max - 1);
+            }
+            let middle = self.generate_character_password(&pools, length - taken.len(), rng);
+            for (k, (min, max)) in taken.iter().zip(saved) {
+                pools[*k].min = min;
+                pools[*k].max = max;
+            }
+
+            first
+                .into_iter()
+                .copied()
+                .chain(middle.chars())
+                .chain(last.copied())
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use rand_chacha::ChaCha20Rng;
+
+    use crate::app::config::{
+        Config,
+        category::{CodePointCategory, PositionRule},
+    };
+
+    // ASCII lowercase letters and digits, where digits may not start the password but must end it.
+    fn letters_then_digit_config() -> Config {
+        let mut config = Config::new();
+        for cat in config.categories.iter_mut() {
+            cat.set_enabled(false);
+        }
+        for name in ["ASCII Lowercase Letters", "ASCII Digits"] {
+            let cat = config.get_categories_by_name_mut(name).next().unwrap();
+            cat.set_enabled(true);
+            cat.set_min_required_chars(0);
+        }
+        let digits = config
+            .get_categories_by_name_mut("ASCII Digits")
+            .next()
+            .unwrap();
+        digits.set_first_rule(PositionRule::Forbidden);
+        digits.set_last_rule(PositionRule::Required);
+        config
+    }
+
+    #[test]
+    fn placed_ends_fit_their_rules() {
+        let mut config = letters_then_digit_config();
+        config.set_password_length(6);
+        assert!(config.places_ends());
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let mut sample = config.get_candidate_sampler();
+        for _ in 0..200 {
+            let password: Vec<char> = sample(&mut rng).chars().collect();
+            assert_eq!(password.len(), 6);
+            assert!(password[0].is_ascii_lowercase());
+            assert!(password[5].is_ascii_digit());
+        }
+    }
+
+    #[test]
+    fn placed_ends_carry_the_entropy_of_their_own_characters() {
+        let mut config = letters_then_digit_config();
+        config.set_password_length(4);
+        let expected = 26f64.log2() + 2.0 * 36f64.log2() + 10f64.log2();
+        assert!((config.get_entropy_bits() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_single_character_must_fit_both_ends() {
+        let mut config = letters_then_digit_config();
+        config.set_password_length(1);
+        let pools = config.get_entropy_pools(&config.get_character_pools());
+        assert!(config.get_end_pools(&pools, 1).is_empty());
+    }
+
+    #[test]
+    fn spaces_stay_off_the_ends_by_default() {
+        let config = Config::new();
+        assert!(!config.fits_position(' ', CodePointCategory::get_first_rule));
+        assert!(!config.fits_position(' ', CodePointCategory::get_last_rule));
+        assert!(config.fits_position('a', CodePointCategory::get_first_rule));
+    }
+}
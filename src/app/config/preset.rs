@@ -0,0 +1,86 @@
+use crate::app::config::{Config, password_rules::PasswordRulesError};
+
+// A named policy from a vendor or a standard, written as passwordrules. Where a source only
+// asks for some of several character classes, every class is required, and required symbols are
+// limited to the ASCII Basic Symbols so they map onto a category exactly.
+pub struct Preset {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub rules: &'static str,
+    // The length to generate, within the bounds of the rules.
+    pub length: usize,
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        id: "aws-iam",
+        name: "AWS IAM",
+        description: "IAM user passwords: 8 to 128 characters, ASCII letters, digits and ! @ # $ % ^ & * ( ) _ + - = [ ] { } | '",
+        rules: "minlength: 8; maxlength: 128; required: upper; required: lower; required: digit; required: [!#$%&*@^]; allowed: [-!#$%&'()*+=@[^_{|}]];",
+        length: 32,
+    },
+    Preset {
+        id: "azure-ad",
+        name: "Azure AD",
+        description: "Microsoft Entra ID: 8 to 256 characters, printable ASCII including space, no Unicode",
+        rules: "minlength: 8; maxlength: 256; required: upper; required: lower; required: digit; required: [!#$%&*@^]; allowed: ascii-printable;",
+        length: 32,
+    },
+    Preset {
+        id: "oracle-db",
+        name: "Oracle Database",
+        description: "ORA12C verify function: 8 to 30 characters with letters and digits, only _ $ # as symbols for unquoted passwords",
+        rules: "minlength: 8; maxlength: 30; required: upper; required: lower; required: digit; allowed: [#$_];",
+        length: 30,
+    },
+    Preset {
+        id: "mysql",
+        name: "MySQL",
+        description: "validate_password MEDIUM policy: at least 8 characters with upper, lower, digit and special; no quotes or backslash",
+        rules: "minlength: 8; maxlength: 32; required: upper; required: lower; required: digit; required: [!#$%&*@^]; allowed: [-!#$%&()*+,./:;<=>?@[^_`{|}~]];",
+        length: 32,
+    },
+    Preset {
+        id: "ldap",
+        name: "LDAP",
+        description: "Avoids characters that need escaping in LDAP DNs and filters: , + \" \\ < > ; = * ( ) #",
+        rules: "minlength: 8; maxlength: 128; required: upper; required: lower; required: digit; allowed: [-!$%&.@^_~];",
+        length: 24,
+    },
+    Preset {
+        id: "wpa2-psk",
+        name: "WPA2 PSK",
+        description: "IEEE 802.11i passphrase: 8 to 63 printable ASCII characters",
+        rules: "minlength: 8; maxlength: 63; allowed: ascii-printable;",
+        length: 63,
+    },
+    Preset {
+        id: "nist-800-63b",
+        name: "NIST SP 800-63B",
+        description: "At least 15 characters for single-factor passwords, up to 64, any Unicode, no composition rules",
+        rules: "minlength: 15; maxlength: 64; allowed: unicode;",
+        length: 20,
+    },
+    Preset {
+        id: "pci-dss",
+        name: "PCI DSS",
+        description: "PCI DSS v4.0 requirement 8.3.6: at least 12 characters with both letters and digits",
+        rules: "minlength: 12; required: lower; required: digit; allowed: upper, special;",
+        length: 16,
+    },
+];
+
+pub fn get_preset(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| {
+        preset.id.eq_ignore_ascii_case(name) || preset.name.eq_ignore_ascii_case(name)
+    })
+}
+
+impl Config {
+    pub fn apply_preset(&mut self, preset: &Preset) -> Result<Vec<String>, PasswordRulesError> {
+        let warnings = self.apply_password_rules(preset.rules)?;
+        self.set_password_length(preset.length);
+        Ok(warnings)
+    }
+}
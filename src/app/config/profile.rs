@@ -0,0 +1,137 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::config::{Config, settings::ConfigSettings};
+
+pub const DEFAULT_PROFILE_NAME: &str = "Default";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Profile {
+    name: String,
+    #[serde(flatten)]
+    settings: ConfigSettings,
+}
+
+impl Profile {
+    pub fn new(name: &str, settings: ConfigSettings) -> Self {
+        Profile {
+            name: name.to_string(),
+            settings,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+}
+
+pub enum ProfileError {
+    EmptyName,
+    NotFound(String),
+    AlreadyExists(String),
+    LastProfile,
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::EmptyName => write!(f, "profile name cannot be empty"),
+            ProfileError::NotFound(name) => write!(f, "no profile named \"{}\"", name),
+            ProfileError::AlreadyExists(name) => {
+                write!(f, "a profile named \"{}\" already exists", name)
+            }
+            ProfileError::LastProfile => write!(f, "the last remaining profile cannot be deleted"),
+        }
+    }
+}
+
+impl Config {
+    pub fn get_active_profile_name(&self) -> &str {
+        self.profiles[self.active_profile].get_name()
+    }
+
+    pub fn get_profile_names(&self) -> Vec<&str> {
+        self.profiles.iter().map(|p| p.get_name()).collect()
+    }
+
+    fn find_profile(&self, name: &str) -> Option<usize> {
+        self.profiles.iter().position(|p| p.name == name)
+    }
+
+    fn validate_new_profile_name(&self, name: &str) -> Result<String, ProfileError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(ProfileError::EmptyName);
+        }
+        match self.find_profile(name) {
+            Some(_) => Err(ProfileError::AlreadyExists(name.to_string())),
+            None => Ok(name.to_string()),
+        }
+    }
+
+    pub fn get_profiles_snapshot(&self) -> Vec<Profile> {
+        let mut profiles = self.profiles.clone();
+        profiles[self.active_profile].settings = self.to_settings();
+        profiles
+    }
+
+    pub fn load_profiles(&mut self, profiles: Vec<Profile>, active_profile: &str) {
+        self.profiles = profiles;
+        self.active_profile = self.find_profile(active_profile).unwrap_or(0);
+        let settings = self.profiles[self.active_profile].settings.clone();
+        self.apply_settings(&settings);
+    }
+
+    pub fn switch_profile(&mut self, name: &str) -> Result<(), ProfileError> {
+        let idx = self
+            .find_profile(name)
+            .ok_or_else(|| ProfileError::NotFound(name.to_string()))?;
+        self.profiles[self.active_profile].settings = self.to_settings();
+        self.active_profile = idx;
+        let settings = self.profiles[idx].settings.clone();
+        self.apply_settings(&settings);
+        Ok(())
+    }
+
+    pub fn create_profile(&mut self, name: &str) -> Result<(), ProfileError> {
+        let name = self.validate_new_profile_name(name)?;
+        self.profiles[self.active_profile].settings = self.to_settings();
+        self.reset_to_defaults();
+        self.profiles.push(Profile::new(&name, self.to_settings()));
+        self.active_profile = self.profiles.len() - 1;
+        Ok(())
+    }
+
+    pub fn duplicate_profile(&mut self, name: &str) -> Result<(), ProfileError> {
+        let name = self.validate_new_profile_name(name)?;
+        self.profiles[self.active_profile].settings = self.to_settings();
+        self.profiles.push(Profile::new(&name, self.to_settings()));
+        self.active_profile = self.profiles.len() - 1;
+        Ok(())
+    }
+
+    pub fn rename_profile(&mut self, name: &str) -> Result<(), ProfileError> {
+        let name = self.validate_new_profile_name(name)?;
+        self.profiles[self.active_profile].name = name;
+        Ok(())
+    }
+
+    pub fn delete_profile(&mut self, name: &str) -> Result<(), ProfileError> {
+        let idx = self
+            .find_profile(name)
+            .ok_or_else(|| ProfileError::NotFound(name.to_string()))?;
+        if self.profiles.len() == 1 {
+            return Err(ProfileError::LastProfile);
+        }
+        self.profiles.remove(idx);
+        if idx == self.active_profile {
+            self.active_profile = 0;
+            let settings = self.profiles[0].settings.clone();
+            self.apply_settings(&settings);
+        } else if idx < self.active_profile {
+            self.active_profile -= 1;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+
+use rand::seq::SliceRandom;
+
+use rand_chacha::ChaCha20Rng;
+
+use serde::{Deserialize, Serialize};
+
+use passgen::{generate_random_chars_from_char_vec, generate_random_words_from_word_vec};
+
+use crate::app::config::{
+    Config,
+    entropy::{log2_factorials, reaches_entropy},
+};
+
+// Every syllable is an onset followed by a vowel. Onsets only contain consonants and vowels only
+// contain vowels, so a password splits back into its syllables in exactly one way and the
+// entropy of each syllable is the log2 of the size of the table.
+const ONSETS: &[&str] = &[
+    "b", "d", "f", "g", "h", "j", "k", "l", "m", "n", "p", "r", "s", "t", "v", "w", "z", "bl",
+    "br", "ch", "dr", "fl", "fr", "gl", "gr", "pl", "pr", "sh", "sl", "sp", "st", "th", "tr",
+];
+const VOWELS: &[&str] = &["a", "e", "i", "o", "u", "ai", "ee", "oa", "oo", "ou"];
+
+const DEFAULT_SYLLABLE_COUNT: usize = 8;
+const MAX_DERIVED_SYLLABLE_COUNT: usize = 1 << 10;
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtraCharacters {
+    pub category: String,
+    pub count: usize,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PronounceableOptions {
+    pub syllable_count: usize,
+    // Characters from other categories, such as digits or symbols, inserted between syllables.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extra_characters: Vec<ExtraCharacters>,
+}
+
+impl Default for PronounceableOptions {
+    fn default() -> Self {
+        PronounceableOptions {
+            syllable_count: DEFAULT_SYLLABLE_COUNT,
+            extra_characters: Vec::new(),
+        }
+    }
+}
+
+pub fn get_syllables() -> Vec<String> {
+    ONSETS
+        .iter()
+        .flat_map(|onset| {
+            VOWELS
+                .iter()
+                .map(move |vowel| format!("{}{}", onset, vowel))
+        })
+        .collect()
+}
+
+impl Config {
+    pub fn get_pronounceable_options(&self) -> &PronounceableOptions {
+        &self.pronounceable
+    }
+
+    pub fn get_pronounceable_options_mut(&mut self) -> &mut PronounceableOptions {
+        &mut self.pronounceable
+    }
+
+    pub fn get_syllable_count(&self) -> usize {
+        self.target_entropy
+            .and_then(|bits| self.get_syllable_count_for_entropy(bits))
+            .unwrap_or(self.pronounceable.syllable_count)
+    }
+
+    pub fn set_syllable_count(&mut self, count: usize) {
+        self.pronounceable.syllable_count = count;
+        self.target_entropy = None;
+    }
+
+    pub fn set_extra_characters(&mut self, category: &str, count: usize) {
+        let extras = &mut self.pronounceable.extra_characters;
+        extras.retain(|extra| !extra.category.eq_ignore_ascii_case(category));
+        if count > 0 {
+            extras.push(ExtraCharacters {
+                category: category.to_string(),
+                count,
+            });
+        }
+    }
+
+    // The active characters of each extra category with its count, skipping unavailable ones.
+    fn get_extra_char_pools(&self) -> Vec<(Vec<char>, usize)> {
+        self.pronounceable
+            .extra_characters
+            .iter()
+            .filter(|extra| extra.count > 0)
+            .filter_map(|extra| {
+                self.get_active_category_by_name(&extra.category)
+                    .map(|cat| (cat.get_active_chars(), extra.count))
+            })
+            .collect()
+    }
+
+    // The syllables and the extra characters are drawn uniformly, and so are the positions of the
+    // extra characters among the syllables and the order of their categories. The last two only
+    // add entropy when they can be told apart in the result, that is when no extra character is
+    // a syllable letter and the extra categories do not overlap; otherwise they are left out to
+    // stay on the safe side.
+    pub fn get_pronounceable_entropy_bits(&self, syllable_count: usize) -> f64 {
+        if syllable_count == 0 {
+            return 0.0;
+        }
+        let mut bits = syllable_count as f64 * (get_syllables().len() as f64).log2();
+
+        let pools = self.get_extra_char_pools();
+        let extra_count: usize = pools.iter().map(|(_, count)| count).sum();
+        for (chars, count) in &pools {
+            bits += *count as f64 * (chars.len() as f64).log2();
+        }
+
+        let mut seen: HashSet<char> = ONSETS
+            .iter()
+            .chain(VOWELS)
+            .flat_map(|s| s.chars())
+            .collect();
+        let distinguishable = pools
+            .iter()
+            .flat_map(|(chars, _)| chars.iter().copied().collect::<HashSet<char>>())
+            .all(|c| seen.insert(c));
+        if distinguishable && extra_count > 0 {
+            let log_fact = log2_factorials(syllable_count + extra_count);
+            bits += log_fact[syllable_count + extra_count]
+                - log_fact[syllable_count]
+                - pools.iter().map(|(_, count)| log_fact[*count]).sum::<f64>();
+        }
+        bits
+    }
+
+    pub fn get_syllable_count_for_entropy(&self, bits: f64) -> Option<usize> {
+        (1..=MAX_DERIVED_SYLLABLE_COUNT)
+            .find(|count| reaches_entropy(self.get_pronounceable_entropy_bits(*count), bits))
+    }
+
+    pub fn generate_pronounceable_password(&self, rng: &mut ChaCha20Rng) -> String {
+        let syllables = get_syllables();
+        let syllables: Vec<&str> = syllables.iter().map(String::as_str).collect();
+
+        let mut extras: Vec<char> = self
+            .get_extra_char_pools()
+            .into_iter()
+            .flat_map(|(chars, count)| generate_random_chars_from_char_vec(&chars, count, rng))
+            .collect();
+        extras.shuffle(rng);
+
+        let mut is_extra: Vec<bool> = vec![false; self.get_syllable_count()];
+        is_extra.extend(vec![true; extras.len()]);
+        is_extra.shuffle(rng);
+
+        let mut syllables =
+            generate_random_words_from_word_vec(&syllables, self.get_syllable_count(), rng)
+                .into_iter();
+        let mut extras = extras.into_iter();
+        let mut result = String::new();
+        for extra in is_extra {
+            match extra {
+                true => result.extend(extras.next()),
+                false => result.extend(syllables.next()),
+            }
+        }
+        result
+    }
+}
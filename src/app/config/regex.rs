@@ -0,0 +1,517 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use rand::Rng;
+
+use rand_chacha::ChaCha20Rng;
+
+use regex_syntax::{
+    ParserBuilder,
+    hir::{Class, ClassUnicode, Hir, HirKind, Look},
+};
+
+use passgen::generate_random_chars_from_char_vec;
+
+use crate::app::config::{
+    Config,
+    entropy::{log2_add, reaches_entropy},
+};
+
+pub const DEFAULT_REGEX: &str = "[A-Za-z][A-Za-z0-9]*[0-9]";
+
+const MAX_NFA_STATES: usize = 1 << 14;
+const MAX_DFA_STATES: usize = 1 << 12;
+pub const MAX_REGEX_LENGTH: usize = 1 << 10;
+
+#[derive(Clone, PartialEq)]
+pub enum RegexError {
+    Syntax(String),
+    Unsupported(&'static str),
+    TooComplex,
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegexError::Syntax(e) => write!(f, "The regex is invalid: {}", e),
+            RegexError::Unsupported(feature) => {
+                write!(f, "The regex uses {}, which is not supported.", feature)
+            }
+            RegexError::TooComplex => write!(
+                f,
+                "The regex is too complex to generate from. Try smaller repetition counts."
+            ),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum CharSet {
+    Literal(char),
+    // Only matches the enabled code points.
+    Class(ClassUnicode),
+}
+
+enum NfaState {
+    Char(usize, usize),
+    Split(Vec<usize>),
+    Start(usize),
+    End(usize),
+    Match,
+}
+
+// A Thompson NFA, compiled back to front: every fragment is built with the state that follows it
+// already known.
+struct Nfa {
+    states: Vec<NfaState>,
+    sets: Vec<CharSet>,
+}
+
+impl Nfa {
+    fn push(&mut self, state: NfaState) -> Result<usize, RegexError> {
+        if self.states.len() >= MAX_NFA_STATES {
+            return Err(RegexError::TooComplex);
+        }
+        self.states.push(state);
+        Ok(self.states.len() - 1)
+    }
+
+    fn push_char(&mut self, set: CharSet, next: usize) -> Result<usize, RegexError> {
+        let index = match self.sets.iter().position(|other| *other == set) {
+            Some(index) => index,
+            None => {
+                self.sets.push(set);
+                self.sets.len() - 1
+            }
+        };
+        self.push(NfaState::Char(index, next))
+    }
+
+    fn compile(&mut self, hir: &Hir, next: usize) -> Result<usize, RegexError> {
+        match hir.kind() {
+            HirKind::Empty => Ok(next),
+            HirKind::Literal(literal) => {
+                let text = std::str::from_utf8(&literal.0)
+                    .map_err(|_| RegexError::Unsupported("non-UTF-8 bytes"))?;
+                text.chars()
+                    .rev()
+                    .try_fold(next, |next, c| self.push_char(CharSet::Literal(c), next))
+            }
+            HirKind::Class(Class::Unicode(class)) => {
+                self.push_char(CharSet::Class(class.clone()), next)
+            }
+            HirKind::Class(Class::Bytes(_)) => Err(RegexError::Unsupported("byte classes")),
+            HirKind::Look(Look::Start) => self.push(NfaState::Start(next)),
+            HirKind::Look(Look::End) => self.push(NfaState::End(next)),
+            HirKind::Look(_) => Err(RegexError::Unsupported(
+                "word boundaries or multi-line anchors",
+            )),
+            HirKind::Repetition(repetition) => {
+                let mut start = match repetition.max {
+                    Some(max) => (repetition.min..max).try_fold(next, |next, _| {
+                        let sub = self.compile(&repetition.sub, next)?;
+                        self.push(NfaState::Split(vec![sub, next]))
+                    })?,
+                    None => {
+                        let split = self.push(NfaState::Split(Vec::new()))?;
+                        let sub = self.compile(&repetition.sub, split)?;
+                        self.states[split] = NfaState::Split(vec![sub, next]);
+                        split
+                    }
+                };
+                for _ in 0..repetition.min {
+                    start = self.compile(&repetition.sub, start)?;
+                }
+                Ok(start)
+            }
+            HirKind::Capture(capture) => self.compile(&capture.sub, next),
+            HirKind::Concat(hirs) => hirs
+                .iter()
+                .rev()
+                .try_fold(next, |next, hir| self.compile(hir, next)),
+            HirKind::Alternation(hirs) => {
+                let starts = hirs
+                    .iter()
+                    .map(|hir| self.compile(hir, next))
+                    .collect::<Result<Vec<usize>, RegexError>>()?;
+                self.push(NfaState::Split(starts))
+            }
+        }
+    }
+
+    // The character and match states reachable from `states` without consuming a character.
+    fn closure(&self, states: &[usize], at_start: bool, at_end: bool) -> Vec<usize> {
+        let mut seen = HashSet::new();
+        let mut stack = states.to_vec();
+        let mut result = Vec::new();
+        while let Some(state) = stack.pop() {
+            if !seen.insert(state) {
+                continue;
+            }
+            match &self.states[state] {
+                NfaState::Char(..) | NfaState::Match => result.push(state),
+                NfaState::Split(next) => stack.extend(next),
+                NfaState::Start(next) if at_start => stack.push(*next),
+                NfaState::End(next) if at_end => stack.push(*next),
+                NfaState::Start(_) | NfaState::End(_) => {}
+            }
+        }
+        result
+    }
+
+    fn accepts(&self, states: &[usize], at_start: bool) -> bool {
+        self.closure(states, at_start, true)
+            .iter()
+            .any(|state| matches!(self.states[*state], NfaState::Match))
+    }
+}
+
+struct DfaState {
+    accepting: bool,
+    transitions: Vec<(usize, usize)>,
+}
+
+// A deterministic automaton over groups of characters that every character class treats alike,
+// so that each string has exactly one path and counting paths counts strings.
+pub struct RegexAutomaton {
+    groups: Vec<Vec<char>>,
+    states: Vec<DfaState>,
+}
+
+impl RegexAutomaton {
+    // The whole password has to match, as if the regex were wrapped in `^(?:...)$`. Literal
+    // characters are always allowed, while classes only match the enabled code points in `pool`.
+    pub fn new(pattern: &str, pool: &[char]) -> Result<Self, RegexError> {
+        let hir = ParserBuilder::new()
+            .build()
+            .parse(pattern)
+            .map_err(|e| RegexError::Syntax(e.to_string()))?;
+        let mut nfa = Nfa {
+            states: Vec::new(),
+            sets: Vec::new(),
+        };
+        let accept = nfa.push(NfaState::Match)?;
+        let start = nfa.compile(&hir, accept)?;
+
+        // Characters that belong to exactly the same sets are interchangeable.
+        let pool_set: HashSet<char> = pool.iter().copied().collect();
+        let mut seen = HashSet::new();
+        let alphabet = pool
+            .iter()
+            .copied()
+            .chain(nfa.sets.iter().filter_map(|set| match set {
+                CharSet::Literal(c) => Some(*c),
+                CharSet::Class(_) => None,
+            }))
+            .filter(|c| seen.insert(*c));
+        let mut group_ids: HashMap<Vec<usize>, usize> = HashMap::new();
+        let mut groups: Vec<(Vec<usize>, Vec<char>)> = Vec::new();
+        for c in alphabet {
+            let signature: Vec<usize> = nfa
+                .sets
+                .iter()
+                .enumerate()
+                .filter(|(_, set)| match set {
+                    CharSet::Literal(l) => *l == c,
+                    CharSet::Class(class) => {
+                        pool_set.contains(&c)
+                            && class
+                                .ranges()
+                                .binary_search_by(|range| match range {
+                                    r if r.end() < c => std::cmp::Ordering::Less,
+                                    r if r.start() > c => std::cmp::Ordering::Greater,
+                                    _ => std::cmp::Ordering::Equal,
+                                })
+                                .is_ok()
+                    }
+                })
+                .map(|(i, _)| i)
+                .collect();
+            if signature.is_empty() {
+                continue;
+            }
+            let id = *group_ids.entry(signature.clone()).or_insert_with(|| {
+                groups.push((signature, Vec::new()));
+                groups.len() - 1
+            });
+            groups[id].1.push(c);
+        }
+
+        // Subset construction. A state is the set of NFA states reached after a character, plus
+        // whether no character has been read yet, which is when `^` can match.
+        let mut ids: HashMap<(Vec<usize>, bool), usize> = HashMap::new();
+        let mut queue = vec![(vec![start], true)];
+        ids.insert((vec![start], true), 0);
+        let mut states: Vec<DfaState> = Vec::new();
+        while states.len() < queue.len() {
+            let (kernel, at_start) = queue[states.len()].clone();
+            let closure = nfa.closure(&kernel, at_start, false);
+            let mut transitions = Vec::new();
+            for (group, (signature, _)) in groups.iter().enumerate() {
+                let mut next: Vec<usize> = closure
+                    .iter()
+                    .filter_map(|state| match nfa.states[*state] {
+                        NfaState::Char(set, next) if signature.binary_search(&set).is_ok() => {
+                            Some(next)
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                if next.is_empty() {
+                    continue;
+                }
+                next.sort_unstable();
+                next.dedup();
+                let key = (next, false);
+                let target = match ids.get(&key) {
+                    Some(target) => *target,
+                    None => {
+                        if queue.len() >= MAX_DFA_STATES {
+                            return Err(RegexError::TooComplex);
+                        }
+                        ids.insert(key.clone(), queue.len());
+                        queue.push(key);
+                        queue.len() - 1
+                    }
+                };
+                transitions.push((group, target));
+            }
+            states.push(DfaState {
+                accepting: nfa.accepts(&kernel, at_start),
+                transitions,
+            });
+        }
+
+        Ok(RegexAutomaton {
+            groups: groups.into_iter().map(|(_, chars)| chars).collect(),
+            states,
+        })
+    }
+
+    // counts[k][s] is log2 of the number of strings of length k that lead from state `s` to a
+    // match, for every k up to `max_length`.
+    fn get_log2_counts(&self, max_length: usize) -> Vec<Vec<f64>> {
+        let mut counts = vec![
+            self.states
+                .iter()
+                .map(|state| match state.accepting {
+                    true => 0.0,
+                    false => f64::NEG_INFINITY,
+                })
+                .collect::<Vec<f64>>(),
+        ];
+        for k in 1..=max_length {
+            let previous = &counts[k - 1];
+            let next = self
+                .states
+                .iter()
+                .map(|state| {
+                    state
+                        .transitions
+                        .iter()
+                        .map(|(group, target)| {
+                            (self.groups[*group].len() as f64).log2() + previous[*target]
+                        })
+                        .fold(f64::NEG_INFINITY, log2_add)
+                })
+                .collect();
+            counts.push(next);
+        }
+        counts
+    }
+
+    // log2 of the number of matching strings of `length` characters, or `None` if there are none.
+    pub fn get_log2_count(&self, length: usize) -> Option<f64> {
+        Some(self.get_log2_counts(length)[length][0]).filter(|count| count.is_finite())
+    }
+
+    pub fn get_length_for_entropy(&self, bits: f64) -> Option<usize> {
+        self.get_log2_counts(MAX_REGEX_LENGTH)
+            .iter()
+            .position(|counts| reaches_entropy(counts[0], bits))
+    }
+
+    // The matching length closest to `length`, preferring the shorter one on a tie.
+    pub fn get_nearest_length(&self, length: usize) -> Option<usize> {
+        let counts = self.get_log2_counts(MAX_REGEX_LENGTH);
+        (0..counts.len())
+            .filter(|k| counts[*k][0].is_finite())
+            .min_by_key(|k| k.abs_diff(length))
+    }
+
+    // Walks the automaton, picking each transition with probability proportional to the number
+    // of matching strings through it, which makes every matching string equally likely.
+    pub fn generate(&self, length: usize, rng: &mut ChaCha20Rng) -> Option<String> {
+        let counts = self.get_log2_counts(length);
+        if !counts[length][0].is_finite() {
+            return None;
+        }
+        let mut result = String::new();
+        let mut state = 0;
+        for remaining in (1..=length).rev() {
+            let total = counts[remaining][state];
+            let mut target: f64 = rng.random();
+            let mut choice = None;
+            for (group, next) in &self.states[state].transitions {
+                let log2_count =
+                    (self.groups[*group].len() as f64).log2() + counts[remaining - 1][*next];
+                if !log2_count.is_finite() {
+                    continue;
+                }
+                choice = Some((*group, *next));
+                target -= (log2_count - total).exp2();
+                if target < 0.0 {
+                    break;
+                }
+            }
+            let (group, next) = choice?;
+            result.extend(generate_random_chars_from_char_vec(
+                &self.groups[group],
+                1,
+                rng,
+            ));
+            state = next;
+        }
+        Some(result)
+    }
+}
+
+impl Config {
+    pub fn get_regex(&self) -> &str {
+        &self.regex
+    }
+
+    pub fn set_regex(&mut self, regex: &str) {
+        self.regex = regex.to_string();
+    }
+
+    pub fn get_regex_automaton(&self) -> Result<RegexAutomaton, RegexError> {
+        RegexAutomaton::new(&self.regex, &self.get_active_chars_for_active_categories())
+    }
+
+    pub fn get_regex_length(&self) -> usize {
+        self.target_entropy
+            .and_then(|bits| {
+                self.get_regex_automaton()
+                    .ok()?
+                    .get_length_for_entropy(bits)
+            })
+            .unwrap_or(self.password_length)
+    }
+
+    // Uniform over the matching strings, so the entropy is the log2 of how many there are.
+    pub fn get_regex_entropy_bits(&self) -> f64 {
+        self.get_regex_automaton()
+            .ok()
+            .and_then(|automaton| automaton.get_log2_count(self.get_regex_length()))
+            .unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rand::SeedableRng;
+
+    use rand_chacha::ChaCha20Rng;
+
+    use super::RegexAutomaton;
+
+    fn automaton(pattern: &str) -> RegexAutomaton {
+        let pool: Vec<char> = ('a'..='z').chain('0'..='9').collect();
+        RegexAutomaton::new(pattern, &pool).ok().unwrap()
+    }
+
+    // The number of matching strings of `length` characters, rounded from its log2.
+    fn count(pattern: &str, length: usize) -> u64 {
+        automaton(pattern)
+            .get_log2_count(length)
+            .map_or(0, |bits| bits.exp2().round() as u64)
+    }
+
+    #[test]
+    fn counts_matching_strings_of_each_length() {
+        assert_eq!(count("[0-9]{3}", 3), 1000);
+        assert_eq!(count("[0-9]{3}", 2), 0);
+        assert_eq!(count("[a-c]*", 0), 1);
+        assert_eq!(count("[a-c]*", 2), 9);
+        assert_eq!(count("a|bc|bd", 1), 1);
+        assert_eq!(count("a|bc|bd", 2), 2);
+        assert_eq!(count("(ab|cd)+", 3), 0);
+        assert_eq!(count("(ab|cd)+", 4), 4);
+        // Classes only match the pool, but literals always match.
+        assert_eq!(count("[A-Z0-1]", 1), 2);
+        assert_eq!(count("X[0-1]", 2), 2);
+    }
+
+    #[test]
+    fn anchors_only_match_at_the_ends() {
+        assert_eq!(count("^ab$", 2), 1);
+        assert_eq!(count("a$|ab", 1), 1);
+        assert_eq!(count("a$|ab", 2), 1);
+        assert_eq!(count("(^a|b)c", 2), 2);
+        assert_eq!(count("b(^a|b)c", 3), 1);
+        for length in 0..4 {
+            assert_eq!(count("a^b", length), 0);
+            assert_eq!(count("a$b", length), 0);
+        }
+    }
+
+    #[test]
+    fn nearest_length_prefers_the_shorter_one_on_a_tie() {
+        assert_eq!(automaton("(ab|cd)+").get_nearest_length(4), Some(4));
+        assert_eq!(automaton("(ab|cd)+").get_nearest_length(5), Some(4));
+        assert_eq!(automaton("(ab|cd)+").get_nearest_length(0), Some(2));
+        assert_eq!(automaton("[0-9]{3}").get_nearest_length(10), Some(3));
+        assert_eq!(automaton("a^b").get_nearest_length(2), None);
+    }
+
+    // Draws `rounds` passwords of `length` characters and checks that each of the `expected`
+    // matching strings comes up within 5% of equally often.
+    fn assert_uniform(pattern: &str, length: usize, expected: &[&str], rounds: usize) {
+        let automaton = automaton(pattern);
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for _ in 0..rounds {
+            *counts
+                .entry(automaton.generate(length, &mut rng).unwrap())
+                .or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), expected.len(), "{:?}", counts);
+        let mean = rounds as f64 / expected.len() as f64;
+        for s in expected {
+            let count = counts.get(*s).copied().unwrap_or(0) as f64;
+            assert!(
+                (count - mean).abs() < mean * 0.05,
+                "{:?} drawn {} times, expected about {}",
+                s,
+                count,
+                mean
+            );
+        }
+    }
+
+    #[test]
+    fn generate_is_uniform_over_matching_strings() {
+        // Each count has a standard deviation of at most about 95, so 5% of the expected count
+        // is more than 5 standard deviations away.
+        assert_uniform("a|bc|bd", 2, &["bc", "bd"], 20_000);
+        assert_uniform(
+            "(ab|cd)+",
+            6,
+            &[
+                "ababab", "ababcd", "abcdab", "abcdcd", "cdabab", "cdabcd", "cdcdab", "cdcdcd",
+            ],
+            80_000,
+        );
+        assert_uniform("a|b[0-1]|c", 2, &["b0", "b1"], 20_000);
+        assert!(
+            automaton("a|bc|bd")
+                .generate(3, &mut ChaCha20Rng::seed_from_u64(0))
+                .is_none()
+        );
+    }
+}
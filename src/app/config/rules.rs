@@ -0,0 +1,229 @@
+pub mod grapheme;
+pub mod keyboard;
+
+use rand::SeedableRng;
+
+use rand_chacha::ChaCha20Rng;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::config::{
+    Config, GenerationMode,
+    category::CodePointCategory,
+    normalization::NormalizationForm,
+    rules::{
+        grapheme::{can_join, has_separate_graphemes},
+        keyboard::{KeyboardLayout, are_keys_adjacent},
+    },
+    script::RestrictionLevel,
+};
+
+// Enough candidates to estimate acceptance rates above a few percent within a small fraction of
+// a bit, while keeping the entropy readout quick.
+pub const ACCEPTANCE_SAMPLES: usize = 500;
+// A fixed seed keeps the estimate, and so the displayed entropy, stable between menus.
+pub const ACCEPTANCE_SEED: u64 = 0;
+
+// Rules checked against every generated candidate. A candidate that breaks one is rejected and
+// drawn again.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RejectionRules {
+    // The most times the same character may appear in a row.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_consecutive: Option<usize>,
+    // The longest allowed run of ascending or descending code points, such as "1234" or "dcba".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_sequence: Option<usize>,
+    // The longest allowed run of adjacent keys on `keyboard_layout`, such as "qwerty" or "1qaz".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_keyboard_run: Option<usize>,
+    pub keyboard_layout: KeyboardLayout,
+}
+
+impl RejectionRules {
+    pub fn is_empty(&self) -> bool {
+        self.max_consecutive.is_none()
+            && self.max_sequence.is_none()
+            && self.max_keyboard_run.is_none()
+    }
+}
+
+// The length of the longest run of consecutive characters where each follows the previous one.
+fn longest_run(chars: &[char], follows: impl Fn(char, char) -> bool) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for (i, c) in chars.iter().enumerate() {
+        current = match i > 0 && follows(chars[i - 1], *c) {
+            true => current + 1,
+            false => 1,
+        };
+        longest = longest.max(current);
+    }
+    longest
+}
+
+impl Config {
+    pub fn get_max_consecutive(&self) -> Option<usize> {
+        self.rules.max_consecutive
+    }
+
+    pub fn set_max_consecutive(&mut self, max: Option<usize>) {
+        self.rules.max_consecutive = max;
+    }
+
+    pub fn get_rejection_rules_mut(&mut self) -> &mut RejectionRules {
+        &mut self.rules
+    }
+
+    pub fn passes_rules(&self, password: &str) -> bool {
+        let chars: Vec<char> = password.chars().collect();
+        let rules = &self.rules;
+        chars
+            .first()
+            .is_none_or(|c| self.fits_position(*c, CodePointCategory::get_first_rule))
+            && chars
+                .last()
+                .is_none_or(|c| self.fits_position(*c, CodePointCategory::get_last_rule))
+            && rules
+                .max_consecutive
+                .is_none_or(|max| longest_run(&chars, |a, b| a == b) <= max)
+            && rules.max_sequence.is_none_or(|max| {
+                longest_run(&chars, |a, b| a as u32 + 1 == b as u32) <= max
+                    && longest_run(&chars, |a, b| b as u32 + 1 == a as u32) <= max
+            })
+            && rules.max_keyboard_run.is_none_or(|max| {
+                let positions = rules.keyboard_layout.get_key_positions();
+                longest_run(&chars, |a, b| {
+                    match (positions.get(&a), positions.get(&b)) {
+                        (Some(a), Some(b)) => are_keys_adjacent(*a, *b),
+                        _ => false,
+                    }
+                }) <= max
+            })
+            && self.passes_restriction_level(password)
+            && self.passes_normalization(password)
+            && self.passes_case_folding(password)
+            && has_separate_graphemes(password)
+    }
+
+    // The share of candidates that pass the rejection rules, estimated by drawing a sample of
+    // them. Placed ends always fit their position rules, so those only count where the ends
+    // are left to rejection.
+    pub fn get_acceptance_rate(&self) -> f64 {
+        match self.accepts_every_candidate() {
+            true => 1.0,
+            false => self.get_sampled_acceptance_rate(self.get_candidate_sampler()),
+        }
+    }
+
+    // The acceptance rate of character passwords of `length` units, which deriving the length
+    // from a target entropy needs before the length is known.
+    pub fn get_acceptance_rate_for_length(&self, length: usize) -> f64 {
+        match self.accepts_every_candidate() {
+            true => 1.0,
+            false => {
+                self.get_sampled_acceptance_rate(self.get_character_sampler_for_length(length))
+            }
+        }
+    }
+
+    fn accepts_every_candidate(&self) -> bool {
+        // Character passwords are drawn from the scripts the restriction level allows, and from
+        // case-stable code points, already.
+        let restricted = (self.restriction_level != RestrictionLevel::Unrestricted
+            || self.case_fold_safe)
+            && self.generation_mode != GenerationMode::Characters;
+        // Other modes can put any code point next to any other, through words, literals or
+        // regex classes.
+        let joinable = self.generation_mode != GenerationMode::Characters
+            || self
+                .get_character_pools()
+                .iter()
+                .flat_map(|pool| &pool.chars)
+                .any(|c| can_join(*c));
+        self.rules.is_empty()
+            && (self.places_ends() || !self.has_position_rules())
+            && !restricted
+            && !joinable
+            && self.normalization == NormalizationForm::None
+    }
+
+    fn get_sampled_acceptance_rate(
+        &self,
+        mut sample: Box<dyn FnMut(&mut ChaCha20Rng) -> String + '_>,
+    ) -> f64 {
+        let mut rng = ChaCha20Rng::seed_from_u64(ACCEPTANCE_SEED);
+        let accepted = (0..ACCEPTANCE_SAMPLES)
+            .filter(|_| self.passes_rules(&sample(&mut rng)))
+            .count();
+        accepted as f64 / ACCEPTANCE_SAMPLES as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::longest_run;
+    use crate::app::config::Config;
+
+    fn digits_config() -> Config {
+        let mut config = Config::new();
+        for cat in config.categories.iter_mut() {
+            cat.set_enabled(false);
+        }
+        let digits = config
+            .get_categories_by_name_mut("ASCII Digits")
+            .next()
+            .unwrap();
+        digits.set_enabled(true);
+        digits.set_min_required_chars(0);
+        config
+    }
+
+    #[test]
+    fn longest_run_counts_the_longest_chain() {
+        let chars: Vec<char> = "aabbbc".chars().collect();
+        assert_eq!(longest_run(&chars, |a, b| a == b), 3);
+        assert_eq!(longest_run(&[], |a, b| a == b), 0);
+    }
+
+    #[test]
+    fn rules_reject_repeats_sequences_and_keyboard_walks() {
+        let mut config = Config::new();
+        assert!(config.passes_rules("aaaa1234qwer"));
+
+        config.get_rejection_rules_mut().max_consecutive = Some(2);
+        assert!(config.passes_rules("aab"));
+        assert!(!config.passes_rules("aaab"));
+
+        let rules = config.get_rejection_rules_mut();
+        rules.max_consecutive = None;
+        rules.max_sequence = Some(3);
+        assert!(config.passes_rules("x123x321x"));
+        assert!(!config.passes_rules("x1234x"));
+        assert!(!config.passes_rules("xdcbax"));
+
+        let rules = config.get_rejection_rules_mut();
+        rules.max_sequence = None;
+        rules.max_keyboard_run = Some(3);
+        assert!(config.passes_rules("qwe-asd"));
+        assert!(!config.passes_rules("qwer"));
+        // Walks can cross rows, and shifted keys sit where their unshifted ones do.
+        assert!(!config.passes_rules("1qaz"));
+        assert!(!config.passes_rules("QWER"));
+    }
+
+    #[test]
+    fn acceptance_rate_estimates_the_share_of_passing_candidates() {
+        let mut config = digits_config();
+        config.set_password_length(2);
+        assert_eq!(config.get_acceptance_rate(), 1.0);
+
+        // Two digits repeat one time in ten.
+        config.set_max_consecutive(Some(1));
+        assert!((config.get_acceptance_rate() - 0.9).abs() < 0.05);
+
+        config.set_max_consecutive(Some(0));
+        assert_eq!(config.get_acceptance_rate(), 0.0);
+    }
+}
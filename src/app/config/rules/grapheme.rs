@@ -0,0 +1,109 @@
+use seshat::unicode::{CodePoint, Ucd, props::Gcb};
+
+// Whether UAX #29 keeps `a` and `b` in one extended grapheme cluster when `b` follows `a`, or the
+// pair renders as one glyph. Every rule that joins more than two code points starts with one of
+// these pairs, so a password with none of them breaks between every two code points.
+fn joins(a: char, b: char) -> bool {
+    let (Ok(a), Ok(b)) = (CodePoint::new(a as u32), CodePoint::new(b as u32)) else {
+        return false;
+    };
+    match (a.gcb(), b.gcb()) {
+        // GB3: a carriage return followed by a line feed.
+        (Gcb::CR, Gcb::LF) => true,
+        // GB4 and GB5: controls break on both sides.
+        (Gcb::CN | Gcb::CR | Gcb::LF, _) | (_, Gcb::CN | Gcb::CR | Gcb::LF) => false,
+        // GB6 to GB8: Hangul syllables built from conjoining jamo.
+        (Gcb::L, Gcb::L | Gcb::V | Gcb::LV | Gcb::LVT)
+        | (Gcb::LV | Gcb::V, Gcb::V | Gcb::T)
+        | (Gcb::LVT | Gcb::T, Gcb::T) => true,
+        // GB9 to GB9b: combining marks, joiners and spacing marks attach to what precedes them,
+        // and prepended marks to what follows. Emoji modifiers extend too since Unicode 11.
+        (_, Gcb::EX | Gcb::ZWJ | Gcb::SM | Gcb::EM) | (Gcb::PP, _) => true,
+        // GB12 and GB13: two regional indicators form a flag.
+        _ if a.ri() && b.ri() => true,
+        // An emoji modifier base followed by a modifier renders as one skin-toned emoji.
+        _ => a.ebase() && b.emod(),
+    }
+}
+
+// Whether every code point of `password` is a grapheme cluster of its own.
+pub fn has_separate_graphemes(password: &str) -> bool {
+    let chars: Vec<char> = password.chars().collect();
+    chars.windows(2).all(|pair| !joins(pair[0], pair[1]))
+}
+
+// Whether `c` can join the code point before it or, as a prepended mark, the one after it. Every
+// joining pair has such a code point, so pools without any always break between code points.
+pub fn can_join(c: char) -> bool {
+    CodePoint::new(c as u32).is_ok_and(|cp| {
+        matches!(
+            cp.gcb(),
+            Gcb::LF
+                | Gcb::L
+                | Gcb::V
+                | Gcb::LV
+                | Gcb::LVT
+                | Gcb::T
+                | Gcb::EX
+                | Gcb::ZWJ
+                | Gcb::SM
+                | Gcb::EM
+                | Gcb::PP
+        ) || cp.ri()
+            || cp.emod()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    use super::{has_separate_graphemes, joins};
+
+    #[test]
+    fn joins_matches_unicode_segmentation() {
+        for password in [
+            // Hangul L+V, L+LV and LV+T, and an LV syllable followed by a leading jamo.
+            "\u{1100}\u{1161}",
+            "\u{1100}\u{AC00}",
+            "\u{AC00}\u{11A8}",
+            "\u{AC00}\u{1100}",
+            // One flag, a flag with a stray regional indicator, and one indicator alone.
+            "\u{1F1FA}\u{1F1F8}",
+            "\u{1F1FA}\u{1F1F8}\u{1F1EB}",
+            "a\u{1F1FA}b",
+            // An emoji base with a skin tone modifier, and a modifier on its own.
+            "\u{1F44D}\u{1F3FD}",
+            "a\u{1F3FD}",
+            // ZWJ sequences, and a joiner between letters.
+            "\u{1F469}\u{200D}\u{1F4BB}",
+            "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}",
+            "a\u{200D}b",
+            // Combining marks, stacked or after a space.
+            "e\u{301}",
+            "a\u{301}\u{323}",
+            " \u{308}",
+            // Line breaks and plain text.
+            "\r\n",
+            "\n\r",
+            "ab1!",
+        ] {
+            let chars: Vec<char> = password.chars().collect();
+            for pair in chars.windows(2) {
+                let text: String = pair.iter().collect();
+                assert_eq!(
+                    joins(pair[0], pair[1]),
+                    text.graphemes(true).count() == 1,
+                    "{:?}",
+                    text
+                );
+            }
+            assert_eq!(
+                has_separate_graphemes(password),
+                password.graphemes(true).count() == chars.len(),
+                "{:?}",
+                password
+            );
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::{collections::HashMap, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+// The unshifted and shifted rows of each layout, from the number row down. Only the keys that
+// produce characters are listed.
+const QWERTY: [(&str, &str); 4] = [
+    ("`1234567890-=", "~!@#$%^&*()_+"),
+    ("qwertyuiop[]\\", "QWERTYUIOP{}|"),
+    ("asdfghjkl;'", "ASDFGHJKL:\""),
+    ("zxcvbnm,./", "ZXCVBNM<>?"),
+];
+const QWERTZ: [(&str, &str); 4] = [
+    ("^1234567890ß´", "°!\"§$%&/()=?`"),
+    ("qwertzuiopü+", "QWERTZUIOPÜ*"),
+    ("asdfghjklöä#", "ASDFGHJKLÖÄ'"),
+    ("yxcvbnm,.-", "YXCVBNM;:_"),
+];
+const AZERTY: [(&str, &str); 4] = [
+    ("²&é\"'(-è_çà)=", "²1234567890°+"),
+    ("azertyuiop^$", "AZERTYUIOP¨£"),
+    ("qsdfghjklmù*", "QSDFGHJKLM%µ"),
+    ("wxcvbn,;:!", "WXCVBN?./§"),
+];
+const DVORAK: [(&str, &str); 4] = [
+    ("`1234567890[]", "~!@#$%^&*(){}"),
+    ("',.pyfgcrl/=\\", "\"<>PYFGCRL?+|"),
+    ("aoeuidhtns-", "AOEUIDHTNS_"),
+    (";qjkxbmwvz", ":QJKXBMWVZ"),
+];
+
+// How far each row is shifted to the right of the number row, in key widths, on a standard
+// staggered keyboard.
+const ROW_OFFSETS: [f64; 4] = [0.0, 1.5, 1.75, 2.25];
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyboardLayout {
+    #[default]
+    Qwerty,
+    Qwertz,
+    Azerty,
+    Dvorak,
+}
+
+impl KeyboardLayout {
+    pub fn get_label(&self) -> &'static str {
+        match self {
+            KeyboardLayout::Qwerty => "QWERTY",
+            KeyboardLayout::Qwertz => "QWERTZ",
+            KeyboardLayout::Azerty => "AZERTY",
+            KeyboardLayout::Dvorak => "Dvorak",
+        }
+    }
+
+    fn get_rows(&self) -> &'static [(&'static str, &'static str); 4] {
+        match self {
+            KeyboardLayout::Qwerty => &QWERTY,
+            KeyboardLayout::Qwertz => &QWERTZ,
+            KeyboardLayout::Azerty => &AZERTY,
+            KeyboardLayout::Dvorak => &DVORAK,
+        }
+    }
+
+    // The row and horizontal position of the key that produces each character, shifted or not.
+    pub fn get_key_positions(&self) -> HashMap<char, (usize, f64)> {
+        let mut positions = HashMap::new();
+        for (row, (unshifted, shifted)) in self.get_rows().iter().enumerate() {
+            for layer in [unshifted, shifted] {
+                for (i, c) in layer.chars().enumerate() {
+                    positions.insert(c, (row, ROW_OFFSETS[row] + i as f64));
+                }
+            }
+        }
+        positions
+    }
+}
+
+impl FromStr for KeyboardLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "qwerty" => Ok(KeyboardLayout::Qwerty),
+            "qwertz" => Ok(KeyboardLayout::Qwertz),
+            "azerty" => Ok(KeyboardLayout::Azerty),
+            "dvorak" => Ok(KeyboardLayout::Dvorak),
+            _ => Err(format!(
+                "unknown keyboard layout \"{}\" (expected qwerty, qwertz, azerty or dvorak)",
+                s
+            )),
+        }
+    }
+}
+
+// Two keys are adjacent when they are next to each other in a row, or touch across neighbouring
+// rows. A key is not adjacent to itself; repeats are covered by the consecutive character rule.
+pub fn are_keys_adjacent(a: (usize, f64), b: (usize, f64)) -> bool {
+    let dx = (a.1 - b.1).abs();
+    match a.0.abs_diff(b.0) {
+        0 => (dx - 1.0).abs() < 1e-9,
+        1 => dx < 1.0,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyboardLayout, are_keys_adjacent};
+
+    fn adjacent(layout: KeyboardLayout, a: char, b: char) -> bool {
+        let positions = layout.get_key_positions();
+        are_keys_adjacent(positions[&a], positions[&b])
+    }
+
+    #[test]
+    fn neighbouring_keys_are_adjacent() {
+        let qwerty = KeyboardLayout::Qwerty;
+        assert!(adjacent(qwerty, 'q', 'w'));
+        assert!(adjacent(qwerty, 'q', 'a'));
+        assert!(adjacent(qwerty, 'a', 'z'));
+        assert!(adjacent(qwerty, '1', 'q'));
+        assert!(adjacent(qwerty, '2', 'q'));
+        assert!(adjacent(qwerty, '!', 'Q'));
+        assert!(!adjacent(qwerty, 'q', 'e'));
+        assert!(!adjacent(qwerty, 'q', 'z'));
+        assert!(!adjacent(qwerty, 'q', 'q'));
+    }
+
+    #[test]
+    fn adjacency_follows_the_layout() {
+        assert!(adjacent(KeyboardLayout::Qwertz, 't', 'z'));
+        assert!(!adjacent(KeyboardLayout::Qwerty, 't', 'z'));
+        assert!(adjacent(KeyboardLayout::Azerty, 'a', 'z'));
+        assert!(adjacent(KeyboardLayout::Dvorak, 'a', 'o'));
+        assert!(!adjacent(KeyboardLayout::Qwerty, 'a', 'o'));
+    }
+}
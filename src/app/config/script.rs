@@ -0,0 +1,300 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use unicode_script::{Script, ScriptExtension, UnicodeScript};
+
+use crate::app::config::{Config, pool::CharacterPool};
+
+// The script combinations UTS #39 accepts at the highly restrictive level besides single scripts.
+const HIGHLY_RESTRICTIVE_SETS: &[&[Script]] = &[
+    &[
+        Script::Latin,
+        Script::Han,
+        Script::Hiragana,
+        Script::Katakana,
+    ],
+    &[Script::Latin, Script::Han, Script::Bopomofo],
+    &[Script::Latin, Script::Han, Script::Hangul],
+];
+
+// The restriction levels of UTS #39, section 5.2, applied to whole passwords.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RestrictionLevel {
+    #[default]
+    Unrestricted,
+    AsciiOnly,
+    SingleScript,
+    HighlyRestrictive,
+    ModeratelyRestrictive,
+}
+
+impl RestrictionLevel {
+    pub fn get_label(&self) -> &'static str {
+        match self {
+            RestrictionLevel::Unrestricted => "Unrestricted",
+            RestrictionLevel::AsciiOnly => "ASCII Only",
+            RestrictionLevel::SingleScript => "Single Script",
+            RestrictionLevel::HighlyRestrictive => "Highly Restrictive",
+            RestrictionLevel::ModeratelyRestrictive => "Moderately Restrictive",
+        }
+    }
+
+    // The largest combinations of the `available` scripts a password may mix at this level.
+    // Combinations contained in another one are left out.
+    fn get_script_sets(&self, available: &[Script]) -> Vec<Vec<Script>> {
+        let mut sets: Vec<Vec<Script>> = available.iter().map(|sc| vec![*sc]).collect();
+        if sets.is_empty() {
+            sets.push(Vec::new());
+        }
+        if matches!(
+            self,
+            RestrictionLevel::HighlyRestrictive | RestrictionLevel::ModeratelyRestrictive
+        ) {
+            sets.extend(HIGHLY_RESTRICTIVE_SETS.iter().map(|set| {
+                set.iter()
+                    .filter(|sc| available.contains(sc))
+                    .copied()
+                    .collect()
+            }));
+        }
+        if *self == RestrictionLevel::ModeratelyRestrictive && available.contains(&Script::Latin) {
+            sets.extend(
+                available
+                    .iter()
+                    .filter(|sc| !matches!(sc, Script::Latin | Script::Cyrillic | Script::Greek))
+                    .map(|sc| vec![Script::Latin, *sc]),
+            );
+        }
+
+        let mut maximal: Vec<Vec<Script>> = Vec::new();
+        sets.sort_by_key(|set| std::cmp::Reverse(set.len()));
+        for set in sets {
+            if !maximal
+                .iter()
+                .any(|other| set.iter().all(|sc| other.contains(sc)))
+            {
+                maximal.push(set);
+            }
+        }
+        maximal
+    }
+}
+
+impl FromStr for RestrictionLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "unrestricted" | "none" => Ok(RestrictionLevel::Unrestricted),
+            "ascii" | "ascii-only" => Ok(RestrictionLevel::AsciiOnly),
+            "single" | "single-script" => Ok(RestrictionLevel::SingleScript),
+            "highly" | "highly-restrictive" => Ok(RestrictionLevel::HighlyRestrictive),
+            "moderately" | "moderately-restrictive" => Ok(RestrictionLevel::ModeratelyRestrictive),
+            _ => Err(format!(
+                "unknown restriction level \"{}\" (expected unrestricted, ascii, single, highly or moderately)",
+                s
+            )),
+        }
+    }
+}
+
+// Common and Inherited characters, such as digits, punctuation and combining marks, go with
+// every script.
+fn is_script_neutral(scripts: ScriptExtension) -> bool {
+    scripts.is_common() || scripts.is_inherited()
+}
+
+// Whether a character with the Script_Extensions `scripts` can be used with the scripts of `set`.
+// UTS #39 resolves scripts through Script_Extensions, so a character shared by a few scripts,
+// such as the ideographic comma, only goes with those. Characters of no known script go with
+// none.
+fn fits_script_set(scripts: ScriptExtension, set: &[Script]) -> bool {
+    is_script_neutral(scripts) || set.iter().any(|sc| scripts.contains_script(*sc))
+}
+
+fn push_unique(available: &mut Vec<Script>, scripts: ScriptExtension) {
+    if is_script_neutral(scripts) {
+        return;
+    }
+    for sc in scripts.iter() {
+        if !available.contains(&sc) {
+            available.push(sc);
+        }
+    }
+}
+
+impl Config {
+    pub fn get_restriction_level(&self) -> RestrictionLevel {
+        self.restriction_level
+    }
+
+    pub fn set_restriction_level(&mut self, level: RestrictionLevel) {
+        self.restriction_level = level;
+    }
+
+    pub fn passes_restriction_level(&self, password: &str) -> bool {
+        let level = self.restriction_level;
+        match level {
+            RestrictionLevel::Unrestricted => true,
+            RestrictionLevel::AsciiOnly => password.is_ascii(),
+            _ => {
+                let scripts: Vec<ScriptExtension> =
+                    password.chars().map(|c| c.script_extension()).collect();
+                let mut available = Vec::new();
+                for sc in &scripts {
+                    push_unique(&mut available, *sc);
+                }
+                level
+                    .get_script_sets(&available)
+                    .iter()
+                    .any(|set| scripts.iter().all(|sc| fits_script_set(*sc, set)))
+            }
+        }
+    }
+
+    // The character pools of every script combination the restriction level allows, each
+    // restricted to the characters of its scripts. Combinations that would leave a category
+    // with a minimum without characters are left out.
+    pub fn get_restricted_pool_sets(&self) -> Vec<Vec<CharacterPool>> {
+        let pools = self.get_character_pools();
+        let level = self.restriction_level;
+        if level == RestrictionLevel::Unrestricted {
+            return vec![pools];
+        }
+
+        let scripts: Vec<Vec<ScriptExtension>> = pools
+            .iter()
+            .map(|pool| pool.chars.iter().map(|c| c.script_extension()).collect())
+            .collect();
+        let sets = match level {
+            RestrictionLevel::AsciiOnly => vec![Vec::new()],
+            _ => {
+                let mut available = Vec::new();
+                for sc in scripts.iter().flatten() {
+                    push_unique(&mut available, *sc);
+                }
+                level.get_script_sets(&available)
+            }
+        };
+
+        sets.iter()
+            .filter_map(|set| {
+                let mut restricted = Vec::new();
+                for (pool, scripts) in pools.iter().zip(&scripts) {
+                    let chars: Vec<char> = pool
+                        .chars
+                        .iter()
+                        .zip(scripts)
+                        .filter(|(c, sc)| match level {
+                            RestrictionLevel::AsciiOnly => c.is_ascii(),
+                            _ => fits_script_set(**sc, set),
+                        })
+                        .map(|(c, _)| *c)
+                        .collect();
+                    match (chars.is_empty(), pool.min) {
+                        (true, 0) => {}
+                        (true, _) => return None,
+                        (false, _) => restricted.push(CharacterPool {
+                            chars,
+                            min: pool.min,
+                            max: pool.max,
+                            weight: pool.weight,
+                        }),
+                    }
+                }
+                (!restricted.is_empty()).then_some(restricted)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use rand_chacha::ChaCha20Rng;
+
+    use super::RestrictionLevel;
+    use crate::app::config::Config;
+
+    const LEVELS: [RestrictionLevel; 5] = [
+        RestrictionLevel::Unrestricted,
+        RestrictionLevel::AsciiOnly,
+        RestrictionLevel::SingleScript,
+        RestrictionLevel::HighlyRestrictive,
+        RestrictionLevel::ModeratelyRestrictive,
+    ];
+
+    // Latin, CJK, European and West Asian characters and digits, none of them required, so
+    // every restriction level has a script combination to draw from.
+    fn mixed_script_config() -> Config {
+        let mut config = Config::new();
+        for cat in config.categories.iter_mut() {
+            cat.set_enabled(false);
+        }
+        for name in [
+            "ASCII Lowercase Letters",
+            "ASCII Digits",
+            "CJK Characters",
+            "European Characters",
+            "West Asian and Middle Eastern Characters",
+        ] {
+            let cat = config.get_categories_by_name_mut(name).next().unwrap();
+            cat.set_enabled(true);
+            cat.set_min_required_chars(0);
+        }
+        config
+    }
+
+    #[test]
+    fn restriction_levels_follow_uts39() {
+        let mut config = Config::new();
+        for (password, levels) in [
+            // Digits and punctuation are Common and go with any script.
+            ("abc1!", [true, true, true, true, true]),
+            ("\u{3B1}\u{3B2}1", [true, false, true, true, true]),
+            // Katakana with the prolonged sound mark, which Hiragana and Katakana share.
+            ("\u{30AB}\u{30FC}", [true, false, true, true, true]),
+            // Latin with Japanese, then Latin with Hebrew, then Latin with Greek.
+            ("a\u{3072}\u{30AB}", [true, false, false, true, true]),
+            ("a\u{5D0}", [true, false, false, false, true]),
+            ("a\u{3B1}", [true, false, false, false, false]),
+            // The ideographic comma is Common by Script but only CJK scripts use it.
+            ("a\u{3001}", [true, false, false, true, true]),
+            ("\u{6F22}\u{3001}", [true, false, true, true, true]),
+        ] {
+            for (level, expected) in LEVELS.into_iter().zip(levels) {
+                config.set_restriction_level(level);
+                assert_eq!(
+                    config.passes_restriction_level(password),
+                    expected,
+                    "{:?} at {}",
+                    password,
+                    level.get_label()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn restricted_pools_only_draw_passing_passwords() {
+        let mut config = mixed_script_config();
+        for level in LEVELS {
+            config.set_restriction_level(level);
+            assert!(config.validate_policy().is_ok());
+            let mut rng = ChaCha20Rng::seed_from_u64(0);
+            let mut sample = config.get_candidate_sampler();
+            for _ in 0..100 {
+                let password = sample(&mut rng);
+                assert!(!password.is_empty());
+                assert!(
+                    config.passes_restriction_level(&password),
+                    "{:?} at {}",
+                    password,
+                    level.get_label()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::{collections::HashSet, env, fmt, fs, io, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::config::{
+    Config, GenerationMode, SamplingMode,
+    category::{DEFAULT_WEIGHT, PositionRule},
+    exclusion::{ConfusableExclusion, UnicodeVersion},
+    hash::TargetHash,
+    length::LengthUnit,
+    normalization::NormalizationForm,
+    passphrase::PassphraseOptions,
+    profile::Profile,
+    pronounceable::PronounceableOptions,
+    regex::DEFAULT_REGEX,
+    rules::RejectionRules,
+    script::RestrictionLevel,
+    template::DEFAULT_TEMPLATE,
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedProfiles {
+    pub active_profile: String,
+    pub profiles: Vec<Profile>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ConfigSettings {
+    #[serde(default)]
+    pub generation_mode: GenerationMode,
+    pub password_length: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_entropy: Option<f64>,
+    #[serde(default)]
+    pub sampling_mode: SamplingMode,
+    #[serde(default)]
+    pub passphrase: PassphraseOptions,
+    #[serde(default)]
+    pub pronounceable: PronounceableOptions,
+    #[serde(default = "default_template")]
+    pub template: String,
+    #[serde(default = "default_regex")]
+    pub regex: String,
+    #[serde(default)]
+    pub rules: RejectionRules,
+    #[serde(default)]
+    pub confusables: ConfusableExclusion,
+    #[serde(default)]
+    pub restriction_level: RestrictionLevel,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_unicode_version: Option<UnicodeVersion>,
+    #[serde(default)]
+    pub normalization: NormalizationForm,
+    #[serde(default)]
+    pub case_fold_safe: bool,
+    #[serde(default)]
+    pub length_unit: LengthUnit,
+    #[serde(default)]
+    pub target_hash: TargetHash,
+    #[serde(default)]
+    pub categories: Vec<CategorySettings>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CategorySettings {
+    pub name: String,
+    pub enabled: bool,
+    pub min_characters: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_characters: Option<usize>,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_code_points: Vec<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first: Option<PositionRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last: Option<PositionRule>,
+}
+
+fn default_weight() -> u32 {
+    DEFAULT_WEIGHT
+}
+
+fn default_template() -> String {
+    DEFAULT_TEMPLATE.to_string()
+}
+
+fn default_regex() -> String {
+    DEFAULT_REGEX.to_string()
+}
+
+pub enum SettingsError {
+    NoConfigDir,
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::NoConfigDir => write!(
+                f,
+                "could not determine the config directory (neither XDG_CONFIG_HOME nor HOME is set)"
+            ),
+            SettingsError::Io(e) => write!(f, "{}", e),
+            SettingsError::Parse(e) => write!(f, "invalid config file: {}", e),
+            SettingsError::Serialize(e) => write!(f, "could not serialize config: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for SettingsError {
+    fn from(e: io::Error) -> Self {
+        SettingsError::Io(e)
+    }
+}
+
+pub fn get_config_dir() -> Result<PathBuf, SettingsError> {
+    match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => Ok(PathBuf::from(dir).join("passgen")),
+        None => env::var_os("HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(|home| PathBuf::from(home).join(".config").join("passgen"))
+            .ok_or(SettingsError::NoConfigDir),
+    }
+}
+
+pub fn get_config_path() -> Result<PathBuf, SettingsError> {
+    Ok(get_config_dir()?.join("config.toml"))
+}
+
+impl SavedProfiles {
+    pub fn read() -> Result<Option<Self>, SettingsError> {
+        let path = get_config_path()?;
+        match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents)
+                .map(Some)
+                .map_err(SettingsError::Parse),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn write(&self) -> Result<PathBuf, SettingsError> {
+        let path = get_config_path()?;
+        let contents = toml::to_string(self).map_err(SettingsError::Serialize)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+}
+
+impl Config {
+    pub fn to_settings(&self) -> ConfigSettings {
+        ConfigSettings {
+            generation_mode: self.generation_mode,
+            password_length: self.password_length,
+            target_entropy: self.target_entropy,
+            sampling_mode: self.sampling_mode,
+            passphrase: self.passphrase.clone(),
+            pronounceable: self.pronounceable.clone(),
+            template: self.template.clone(),
+            regex: self.regex.clone(),
+            rules: self.rules.clone(),
+            confusables: self.confusables,
+            restriction_level: self.restriction_level,
+            max_unicode_version: self.max_unicode_version,
+            normalization: self.normalization,
+            case_fold_safe: self.case_fold_safe,
+            length_unit: self.length_unit,
+            target_hash: self.target_hash,
+            categories: self
+                .categories
+                .iter()
+                .map(|cat| CategorySettings {
+                    name: cat.get_name().to_string(),
+                    enabled: cat.is_enabled(),
+                    min_characters: cat.get_min_required_chars(),
+                    max_characters: cat.get_max_allowed_chars(),
+                    weight: cat.get_weight(),
+                    disabled_code_points: cat
+                        .code_points
+                        .iter()
+                        .filter(|cpc| !cpc.is_enabled())
+                        .map(|cpc| cpc.get_u32())
+                        .collect(),
+                    first: Some(cat.get_first_rule()),
+                    last: Some(cat.get_last_rule()),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn apply_settings(&mut self, settings: &ConfigSettings) {
+        self.reset_to_defaults();
+        self.generation_mode = settings.generation_mode;
+        self.password_length = settings.password_length;
+        self.target_entropy = settings.target_entropy;
+        self.sampling_mode = settings.sampling_mode;
+        self.passphrase = settings.passphrase.clone();
+        self.pronounceable = settings.pronounceable.clone();
+        self.template = settings.template.clone();
+        self.regex = settings.regex.clone();
+        self.rules = settings.rules.clone();
+        self.confusables = settings.confusables;
+        self.restriction_level = settings.restriction_level;
+        self.max_unicode_version = settings.max_unicode_version;
+        self.normalization = settings.normalization;
+        self.case_fold_safe = settings.case_fold_safe;
+        self.length_unit = settings.length_unit;
+        self.target_hash = settings.target_hash;
+        for cat_settings in &settings.categories {
+            let disabled: HashSet<u32> =
+                cat_settings.disabled_code_points.iter().copied().collect();
+            for cat in self.get_categories_by_name_mut(&cat_settings.name) {
+                cat.set_enabled(cat_settings.enabled);
+                cat.set_min_required_chars(cat_settings.min_characters);
+                cat.set_max_allowed_chars(cat_settings.max_characters);
+                cat.set_weight(cat_settings.weight);
+                if let Some(rule) = cat_settings.first {
+                    cat.set_first_rule(rule);
+                }
+                if let Some(rule) = cat_settings.last {
+                    cat.set_last_rule(rule);
+                }
+                for cpc in cat.code_points.iter_mut() {
+                    cpc.set_enabled(!disabled.contains(&cpc.get_u32()));
+                }
+            }
+        }
+        self.update_exclusions();
+    }
+
+    pub fn load(&mut self) -> Result<bool, SettingsError> {
+        match SavedProfiles::read()? {
+            Some(saved) if !saved.profiles.is_empty() => {
+                self.load_profiles(saved.profiles, &saved.active_profile);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    pub fn save(&self) -> Result<PathBuf, SettingsError> {
+        SavedProfiles {
+            active_profile: self.get_active_profile_name().to_string(),
+            profiles: self.get_profiles_snapshot(),
+        }
+        .write()
+    }
+}
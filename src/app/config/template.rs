@@ -0,0 +1,209 @@
+use std::{collections::HashSet, fmt};
+
+use rand_chacha::ChaCha20Rng;
+
+use passgen::generate_random_chars_from_char_vec;
+
+use crate::app::config::Config;
+
+pub const DEFAULT_TEMPLATE: &str =
+    "{ASCII Uppercase Letters:4}-{ASCII Digits:4}-{ASCII Lowercase Letters:4}";
+
+// A template is literal text with placeholders. `{Name}` draws one character from the enabled code
+// points of the named category, `{Name:4}` draws four, and `{Name|Other Name:4}` draws from the
+// union of both categories. `{{` and `}}` stand for literal braces.
+#[derive(Clone, PartialEq)]
+pub enum TemplatePart {
+    Literal(String),
+    Placeholder {
+        categories: Vec<String>,
+        count: usize,
+    },
+}
+
+#[derive(Clone, PartialEq)]
+pub enum TemplateError {
+    Unclosed(usize),
+    Unopened(usize),
+    EmptyPlaceholder(usize),
+    InvalidCount { position: usize, count: String },
+    UnknownCategory(String),
+    NoCodePoints(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Unclosed(position) => write!(
+                f,
+                "The placeholder opened at position {} of the template is never closed.",
+                position
+            ),
+            TemplateError::Unopened(position) => write!(
+                f,
+                "The \"}}\" at position {} of the template does not close a placeholder. Use \"}}}}\" for a literal brace.",
+                position
+            ),
+            TemplateError::EmptyPlaceholder(position) => write!(
+                f,
+                "The placeholder at position {} of the template is missing a category name.",
+                position
+            ),
+            TemplateError::InvalidCount { position, count } => write!(
+                f,
+                "The placeholder at position {} of the template has an invalid count \"{}\".",
+                position, count
+            ),
+            TemplateError::UnknownCategory(name) => write!(
+                f,
+                "The template refers to an unknown category \"{}\".",
+                name
+            ),
+            TemplateError::NoCodePoints(name) => write!(
+                f,
+                "The template draws from \"{}\", which has no enabled code points.",
+                name
+            ),
+        }
+    }
+}
+
+// Positions in errors are 1-based character offsets into the template.
+pub fn parse_template(template: &str) -> Result<Vec<TemplatePart>, TemplateError> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars().enumerate().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '{' | '}' if chars.peek().is_some_and(|(_, next)| *next == c) => {
+                chars.next();
+                literal.push(c);
+            }
+            '}' => return Err(TemplateError::Unopened(i + 1)),
+            '{' => {
+                let mut body = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '}')) => break,
+                        Some((_, '{')) | None => return Err(TemplateError::Unclosed(i + 1)),
+                        Some((_, c)) => body.push(c),
+                    }
+                }
+
+                let (names, count) = match body.rsplit_once(':') {
+                    Some((names, count)) => match count.trim().parse::<usize>() {
+                        Ok(count) => (names, count),
+                        Err(_) => {
+                            return Err(TemplateError::InvalidCount {
+                                position: i + 1,
+                                count: count.trim().to_string(),
+                            });
+                        }
+                    },
+                    None => (body.as_str(), 1),
+                };
+                let categories: Vec<String> = names
+                    .split('|')
+                    .map(|name| name.trim().to_string())
+                    .collect();
+                if categories.iter().any(|name| name.is_empty()) {
+                    return Err(TemplateError::EmptyPlaceholder(i + 1));
+                }
+
+                if !literal.is_empty() {
+                    parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(TemplatePart::Placeholder { categories, count });
+            }
+            _ => literal.push(c),
+        }
+    }
+
+    if !literal.is_empty() {
+        parts.push(TemplatePart::Literal(literal));
+    }
+    Ok(parts)
+}
+
+// A template part with its placeholders resolved to the characters they draw from.
+pub enum ResolvedPart {
+    Literal(String),
+    Chars(Vec<char>, usize),
+}
+
+impl Config {
+    pub fn get_template(&self) -> &str {
+        &self.template
+    }
+
+    pub fn set_template(&mut self, template: &str) {
+        self.template = template.to_string();
+    }
+
+    // Placeholders use the enabled code points of their categories even when the category itself
+    // is disabled, since the template names them explicitly.
+    pub fn resolve_template(&self) -> Result<Vec<ResolvedPart>, TemplateError> {
+        parse_template(&self.template)?
+            .into_iter()
+            .map(|part| match part {
+                TemplatePart::Literal(text) => Ok(ResolvedPart::Literal(text)),
+                TemplatePart::Placeholder { categories, count } => {
+                    let mut seen = HashSet::new();
+                    let mut chars = Vec::new();
+                    for name in &categories {
+                        let cat = self
+                            .categories
+                            .iter()
+                            .find(|cat| cat.get_name().eq_ignore_ascii_case(name))
+                            .ok_or_else(|| TemplateError::UnknownCategory(name.clone()))?;
+                        chars.extend(
+                            cat.get_active_chars()
+                                .into_iter()
+                                .filter(|c| seen.insert(*c)),
+                        );
+                    }
+                    match chars.is_empty() {
+                        true => Err(TemplateError::NoCodePoints(categories.join("|"))),
+                        false => Ok(ResolvedPart::Chars(chars, count)),
+                    }
+                }
+            })
+            .collect()
+    }
+
+    pub fn validate_template(&self) -> Result<(), TemplateError> {
+        self.resolve_template().map(|_| ())
+    }
+
+    // Every placeholder position is drawn independently and uniformly, and literal text adds
+    // nothing.
+    pub fn get_template_entropy_bits(&self) -> f64 {
+        self.resolve_template()
+            .map(|parts| {
+                parts
+                    .iter()
+                    .map(|part| match part {
+                        ResolvedPart::Literal(_) => 0.0,
+                        ResolvedPart::Chars(chars, count) => {
+                            *count as f64 * (chars.len() as f64).log2()
+                        }
+                    })
+                    .sum()
+            })
+            .unwrap_or(0.0)
+    }
+}
+
+pub fn generate_from_template(parts: &[ResolvedPart], rng: &mut ChaCha20Rng) -> String {
+    let mut result = String::new();
+    for part in parts {
+        match part {
+            ResolvedPart::Literal(text) => result.push_str(text),
+            ResolvedPart::Chars(chars, count) => {
+                result.extend(generate_random_chars_from_char_vec(chars, *count, rng))
+            }
+        }
+    }
+    result
+}
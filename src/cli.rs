@@ -0,0 +1,546 @@
+use std::{fmt, process::ExitCode, str::FromStr};
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::app::config::{
+    Config, GenerationMode, SamplingMode,
+    category::{CodePointCategory, PositionRule},
+    entropy::format_entropy,
+    exclusion::{ConfusableExclusion, UnicodeVersion},
+    hash::TargetHash,
+    length::{LengthUnit, get_length_summary},
+    normalization::NormalizationForm,
+    passphrase::{Capitalization, Wordlist},
+    password_rules::PasswordRulesError,
+    policy::PolicyError,
+    preset::{PRESETS, get_preset},
+    profile::ProfileError,
+    rules::keyboard::KeyboardLayout,
+    script::RestrictionLevel,
+    settings::SettingsError,
+};
+
+#[derive(Parser)]
+#[command(
+    name = "passgen",
+    version,
+    about = "Passgen - A Secure Password Generator",
+    long_about = "Passgen - A Secure Password Generator\n\nRun without a subcommand to start the interactive UI."
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Generate one or more passwords and print them to stdout
+    Generate(Box<GenerateArgs>),
+    /// Print the configuration as a passwordrules policy string
+    Rules(Box<GenerateArgs>),
+    /// List the available character categories
+    Categories,
+    /// List the saved profiles
+    Profiles,
+    /// List the built-in vendor and compliance presets
+    Presets,
+}
+
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// Password length in characters
+    #[arg(short, long)]
+    length: Option<usize>,
+
+    /// What the password length counts: chars, bytes, utf16 or graphemes
+    #[arg(long, value_name = "UNIT")]
+    unit: Option<LengthUnit>,
+
+    /// Password hash the passwords are stored with, to warn when it ignores part of them: none, bcrypt, scrypt, argon2, pbkdf2 or ntlm
+    #[arg(long, value_name = "HASH")]
+    hash: Option<TargetHash>,
+
+    /// Derive the length from the required entropy in bits instead
+    #[arg(short, long, value_name = "BITS", conflicts_with = "length")]
+    entropy: Option<f64>,
+
+    /// Enable only the named categories (repeatable)
+    #[arg(short, long = "category", value_name = "NAME")]
+    categories: Vec<String>,
+
+    /// Disable the named category (repeatable)
+    #[arg(long = "no-category", value_name = "NAME")]
+    no_categories: Vec<String>,
+
+    /// Minimum number of characters from a category (repeatable)
+    #[arg(short, long = "min", value_name = "NAME=COUNT", value_parser = parse_category_value::<usize>)]
+    min_characters: Vec<(String, usize)>,
+
+    /// Generate with the settings of the named saved profile
+    #[arg(short, long, value_name = "NAME", conflicts_with = "defaults")]
+    profile: Option<String>,
+
+    /// Maximum number of characters from a category (repeatable)
+    #[arg(long = "max", value_name = "NAME=COUNT", value_parser = parse_category_value::<usize>)]
+    max_characters: Vec<(String, usize)>,
+
+    /// Exact number of characters from a category (repeatable)
+    #[arg(long = "exact", value_name = "NAME=COUNT", value_parser = parse_category_value::<usize>)]
+    exact_characters: Vec<(String, usize)>,
+
+    /// Whether a category may start the password: allowed, forbidden or required (repeatable)
+    #[arg(long = "first", value_name = "NAME=RULE", value_parser = parse_category_value::<PositionRule>)]
+    first_rules: Vec<(String, PositionRule)>,
+
+    /// Whether a category may end the password: allowed, forbidden or required (repeatable)
+    #[arg(long = "last", value_name = "NAME=RULE", value_parser = parse_category_value::<PositionRule>)]
+    last_rules: Vec<(String, PositionRule)>,
+
+    /// Exclude confusable characters: none, ascii (0 O o 1 l I | ` ' ") or uts39
+    #[arg(long, value_name = "SET")]
+    confusables: Option<ConfusableExclusion>,
+
+    /// Limit the scripts a password may mix: unrestricted, ascii, single, highly or moderately
+    #[arg(long, value_name = "LEVEL")]
+    restriction: Option<RestrictionLevel>,
+
+    /// Only use characters assigned in this Unicode version or earlier, such as 6.0
+    #[arg(long, value_name = "VERSION")]
+    max_unicode_version: Option<UnicodeVersion>,
+
+    /// Only use characters, and passwords, that the server's normalization leaves unchanged: none, nfc, nfkc or precis
+    #[arg(long, value_name = "FORM")]
+    normalization: Option<NormalizationForm>,
+
+    /// Only use characters that lowercasing or case folding leaves unchanged, for case-insensitive backends
+    #[arg(long)]
+    case_insensitive: bool,
+
+    /// Pick a category by weight before picking a code point within it
+    #[arg(short, long)]
+    weighted: bool,
+
+    /// Weight of a category in weighted sampling mode; implies --weighted (repeatable)
+    #[arg(long = "weight", value_name = "NAME=WEIGHT", value_parser = parse_category_value::<u32>)]
+    weights: Vec<(String, u32)>,
+
+    /// Generate a passphrase of random words instead of random characters
+    #[arg(short = 'P', long, conflicts_with = "length")]
+    passphrase: bool,
+
+    /// Number of words in the passphrase; implies --passphrase
+    #[arg(long, value_name = "COUNT", conflicts_with_all = ["length", "entropy"])]
+    words: Option<usize>,
+
+    /// Wordlist to draw passphrase words from: large or short; implies --passphrase
+    #[arg(long, value_name = "LIST")]
+    wordlist: Option<Wordlist>,
+
+    /// Text placed between passphrase words; implies --passphrase
+    #[arg(long, value_name = "TEXT")]
+    separator: Option<String>,
+
+    /// Draw each separator from the named category instead; implies --passphrase
+    #[arg(long, value_name = "NAME", conflicts_with = "separator")]
+    separator_category: Option<String>,
+
+    /// Capitalization of passphrase words: lower, words, random or upper; implies --passphrase
+    #[arg(long, value_name = "STYLE")]
+    capitalize: Option<Capitalization>,
+
+    /// Number of random digits appended to one passphrase word; implies --passphrase
+    #[arg(long, value_name = "COUNT")]
+    digits: Option<usize>,
+
+    /// Generate a pronounceable password of random syllables instead of random characters
+    #[arg(long, conflicts_with_all = ["length", "passphrase", "words"])]
+    pronounceable: bool,
+
+    /// Number of syllables in the pronounceable password; implies --pronounceable
+    #[arg(long, value_name = "COUNT", conflicts_with_all = ["length", "entropy", "passphrase", "words"])]
+    syllables: Option<usize>,
+
+    /// Characters from a category placed between syllables; implies --pronounceable (repeatable)
+    #[arg(long = "extra", value_name = "NAME=COUNT", value_parser = parse_category_value::<usize>, conflicts_with_all = ["length", "passphrase", "words"])]
+    extra_characters: Vec<(String, usize)>,
+
+    /// Generate from a template such as "{ASCII Uppercase Letters:4}-{ASCII Digits:4}"
+    #[arg(short, long, value_name = "TEMPLATE", conflicts_with_all = ["length", "passphrase", "words", "pronounceable", "syllables", "extra_characters"])]
+    template: Option<String>,
+
+    /// Generate strings of the password length that fully match a regex
+    #[arg(short, long, value_name = "REGEX", conflicts_with_all = ["passphrase", "words", "pronounceable", "syllables", "extra_characters", "template"])]
+    regex: Option<String>,
+
+    /// Start from a built-in vendor or compliance preset (run `passgen presets` to list them)
+    #[arg(long, value_name = "NAME", conflicts_with = "passwordrules")]
+    preset: Option<String>,
+
+    /// Start from a passwordrules policy string such as "minlength: 20; required: lower; required: digit;"
+    #[arg(long, value_name = "RULES")]
+    passwordrules: Option<String>,
+
+    /// Reject passwords that repeat a character more than COUNT times in a row
+    #[arg(long, value_name = "COUNT")]
+    max_consecutive: Option<usize>,
+
+    /// Reject passwords with more than COUNT ascending or descending characters in a row
+    #[arg(long, value_name = "COUNT")]
+    max_sequence: Option<usize>,
+
+    /// Reject passwords with more than COUNT adjacent keys in a row
+    #[arg(long, value_name = "COUNT")]
+    max_keyboard_run: Option<usize>,
+
+    /// Keyboard layout for --max-keyboard-run: qwerty, qwertz, azerty or dvorak
+    #[arg(long, value_name = "LAYOUT")]
+    keyboard_layout: Option<KeyboardLayout>,
+
+    /// Ignore the saved configuration and start from the built-in defaults
+    #[arg(long)]
+    defaults: bool,
+
+    /// Print the entropy of the resulting configuration to stderr
+    #[arg(long)]
+    show_entropy: bool,
+
+    /// Print the length of each password in code points, UTF-8 bytes, UTF-16 units and grapheme clusters to stderr
+    #[arg(long)]
+    show_lengths: bool,
+
+    /// Number of passwords to generate
+    #[arg(short = 'n', long, default_value_t = 1)]
+    count: usize,
+}
+
+pub enum CliError {
+    Settings(SettingsError),
+    Profile(ProfileError),
+    UnknownCategory(String),
+    UnknownPreset(String),
+    PasswordRules(PasswordRulesError),
+    Policy(Vec<PolicyError>),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Settings(e) => write!(f, "could not load saved configuration: {}", e),
+            CliError::Profile(e) => write!(f, "{}", e),
+            CliError::UnknownCategory(name) => write!(
+                f,
+                "unknown category \"{}\" (run `passgen categories` to list them)",
+                name
+            ),
+            CliError::UnknownPreset(name) => write!(
+                f,
+                "unknown preset \"{}\" (run `passgen presets` to list them)",
+                name
+            ),
+            CliError::PasswordRules(e) => write!(f, "{}", e),
+            CliError::Policy(errors) => {
+                write!(f, "the configuration cannot generate a password:")?;
+                for e in errors {
+                    write!(f, "\n  - {}", e)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn parse_category_value<T: FromStr>(input: &str) -> Result<(String, T), String> {
+    let (name, value) = input
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got \"{}\"", input))?;
+    let value = value
+        .trim()
+        .parse::<T>()
+        .map_err(|_| format!("invalid value \"{}\"", value))?;
+    Ok((name.trim().to_string(), value))
+}
+
+pub fn run(command: Command) -> ExitCode {
+    let result = match command {
+        Command::Generate(args) => generate(*args),
+        Command::Rules(args) => print_password_rules(*args),
+        Command::Categories => {
+            list_categories();
+            Ok(())
+        }
+        Command::Profiles => list_profiles(),
+        Command::Presets => {
+            list_presets();
+            Ok(())
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("passgen: error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn list_categories() {
+    let config = Config::new();
+    for cat in config.get_all_categories() {
+        println!("{}", cat.get_name());
+    }
+}
+
+fn list_profiles() -> Result<(), CliError> {
+    let mut config = Config::new();
+    config.load().map_err(CliError::Settings)?;
+    for name in config.get_profile_names() {
+        match name == config.get_active_profile_name() {
+            true => println!("{} (active)", name),
+            false => println!("{}", name),
+        }
+    }
+    Ok(())
+}
+
+fn list_presets() {
+    for preset in PRESETS {
+        println!("{:<14} {}: {}", preset.id, preset.name, preset.description);
+    }
+}
+
+fn apply_to_categories(
+    config: &mut Config,
+    name: &str,
+    mut f: impl FnMut(&mut CodePointCategory),
+) -> Result<(), CliError> {
+    let mut found = false;
+    for cat in config.get_categories_by_name_mut(name) {
+        f(cat);
+        found = true;
+    }
+    match found {
+        true => Ok(()),
+        false => Err(CliError::UnknownCategory(name.to_string())),
+    }
+}
+
+fn build_config(args: &GenerateArgs) -> Result<Config, CliError> {
+    let mut config = Config::new();
+
+    if !args.defaults {
+        config.load().map_err(CliError::Settings)?;
+    }
+
+    if let Some(profile) = &args.profile {
+        config.switch_profile(profile).map_err(CliError::Profile)?;
+    }
+
+    let warnings = match (&args.preset, &args.passwordrules) {
+        (Some(name), _) => {
+            let preset = get_preset(name).ok_or_else(|| CliError::UnknownPreset(name.clone()))?;
+            config.apply_preset(preset)
+        }
+        (None, Some(rules)) => config.apply_password_rules(rules),
+        (None, None) => Ok(Vec::new()),
+    }
+    .map_err(CliError::PasswordRules)?;
+    for warning in warnings {
+        eprintln!("passgen: warning: {}", warning);
+    }
+
+    if !args.categories.is_empty() {
+        config
+            .categories
+            .iter_mut()
+            .for_each(|cat| cat.set_enabled(false));
+        for name in &args.categories {
+            apply_to_categories(&mut config, name, |cat| cat.set_enabled(true))?;
+        }
+    }
+
+    for name in &args.no_categories {
+        apply_to_categories(&mut config, name, |cat| cat.set_enabled(false))?;
+    }
+
+    for (name, count) in &args.min_characters {
+        apply_to_categories(&mut config, name, |cat| cat.set_min_required_chars(*count))?;
+    }
+
+    for (name, count) in &args.max_characters {
+        apply_to_categories(&mut config, name, |cat| {
+            cat.set_max_allowed_chars(Some(*count))
+        })?;
+    }
+
+    for (name, count) in &args.exact_characters {
+        apply_to_categories(&mut config, name, |cat| {
+            cat.set_min_required_chars(*count);
+            cat.set_max_allowed_chars(Some(*count));
+        })?;
+    }
+
+    if let Some(exclusion) = args.confusables {
+        config.set_confusable_exclusion(exclusion);
+    }
+
+    if let Some(version) = args.max_unicode_version {
+        config.set_max_unicode_version(Some(version));
+    }
+
+    if let Some(form) = args.normalization {
+        config.set_normalization_form(form);
+    }
+
+    if args.case_insensitive {
+        config.set_case_fold_safe(true);
+    }
+
+    if let Some(level) = args.restriction {
+        config.set_restriction_level(level);
+    }
+
+    for (name, rule) in &args.first_rules {
+        apply_to_categories(&mut config, name, |cat| cat.set_first_rule(*rule))?;
+    }
+
+    for (name, rule) in &args.last_rules {
+        apply_to_categories(&mut config, name, |cat| cat.set_last_rule(*rule))?;
+    }
+
+    for (name, weight) in &args.weights {
+        apply_to_categories(&mut config, name, |cat| cat.set_weight(*weight))?;
+    }
+
+    if args.weighted || !args.weights.is_empty() {
+        config.set_sampling_mode(SamplingMode::Weighted);
+    }
+
+    if let Some(length) = args.length {
+        if args.regex.is_none() {
+            config.set_generation_mode(GenerationMode::Characters);
+        }
+        config.set_password_length(length);
+    }
+
+    if let Some(unit) = args.unit {
+        config.set_length_unit(unit);
+    }
+
+    if let Some(hash) = args.hash {
+        config.set_target_hash(hash);
+    }
+
+    if let Some(name) = &args.separator_category {
+        apply_to_categories(&mut config, name, |_| {})?;
+    }
+
+    let options = config.get_passphrase_options_mut();
+    if let Some(wordlist) = args.wordlist {
+        options.wordlist = wordlist;
+    }
+    if let Some(separator) = &args.separator {
+        options.separator = separator.clone();
+        options.separator_category = None;
+    }
+    if let Some(name) = &args.separator_category {
+        options.separator_category = Some(name.clone());
+    }
+    if let Some(capitalization) = args.capitalize {
+        options.capitalization = capitalization;
+    }
+    if let Some(digits) = args.digits {
+        options.digits = digits;
+    }
+    if let Some(words) = args.words {
+        config.set_word_count(words);
+    }
+
+    if args.passphrase
+        || args.words.is_some()
+        || args.wordlist.is_some()
+        || args.separator.is_some()
+        || args.separator_category.is_some()
+        || args.capitalize.is_some()
+        || args.digits.is_some()
+    {
+        config.set_generation_mode(GenerationMode::Passphrase);
+    }
+
+    for (name, count) in &args.extra_characters {
+        apply_to_categories(&mut config, name, |_| {})?;
+        config.set_extra_characters(name, *count);
+    }
+    if let Some(syllables) = args.syllables {
+        config.set_syllable_count(syllables);
+    }
+
+    if args.pronounceable || args.syllables.is_some() || !args.extra_characters.is_empty() {
+        config.set_generation_mode(GenerationMode::Pronounceable);
+    }
+
+    if let Some(regex) = &args.regex {
+        config.set_regex(regex);
+        config.set_generation_mode(GenerationMode::Regex);
+    }
+
+    if let Some(template) = &args.template {
+        config.set_template(template);
+        config.set_generation_mode(GenerationMode::Template);
+    }
+
+    if let Some(bits) = args.entropy {
+        config.set_target_entropy(Some(bits));
+    }
+
+    if let Some(max) = args.max_consecutive {
+        config.set_max_consecutive(Some(max));
+    }
+    let rules = config.get_rejection_rules_mut();
+    if let Some(max) = args.max_sequence {
+        rules.max_sequence = Some(max);
+    }
+    if let Some(max) = args.max_keyboard_run {
+        rules.max_keyboard_run = Some(max);
+    }
+    if let Some(layout) = args.keyboard_layout {
+        rules.keyboard_layout = layout;
+    }
+
+    config.validate_policy().map_err(CliError::Policy)?;
+
+    Ok(config)
+}
+
+fn generate(args: GenerateArgs) -> Result<(), CliError> {
+    let config = build_config(&args)?;
+    if let Some(warning) = config.get_hash_warning() {
+        eprintln!("passgen: warning: {}", warning);
+    }
+    if args.show_entropy {
+        eprintln!("entropy: {}", config.get_entropy_label());
+        if config.get_target_hash().get_limit().is_some() {
+            eprintln!(
+                "effective entropy: {}",
+                format_entropy(config.get_effective_entropy_bits())
+            );
+        }
+    }
+    for _ in 0..args.count {
+        let password = config.generate_password();
+        println!("{}", password);
+        if args.show_lengths {
+            eprintln!("length: {}", get_length_summary(&password));
+        }
+    }
+    Ok(())
+}
+
+fn print_password_rules(args: GenerateArgs) -> Result<(), CliError> {
+    let config = build_config(&args)?;
+    let rules = config
+        .to_password_rules()
+        .map_err(CliError::PasswordRules)?;
+    println!("{}", rules);
+    Ok(())
+}
@@ -1,23 +1,114 @@
 use rand::{
     SeedableRng, TryRngCore,
-    distr::{Distribution, slice::Choose},
+    distr::{Distribution, slice::Choose, weighted::WeightedIndex},
     rngs::OsRng,
 };
 use rand_chacha::ChaCha20Rng;
 
 pub fn generate_random_chars_from_char_vec(
-    chars: Vec<char>,
+    chars: &[char],
     num_chars: usize,
     rng: &mut ChaCha20Rng,
 ) -> Vec<char> {
-    Choose::new(&chars)
+    Choose::new(chars)
         .unwrap()
         .sample_iter(rng)
         .take(num_chars)
-        .map(|c| *c)
+        .copied()
         .collect()
 }
 
+pub fn generate_weighted_random_chars_from_char_vecs(
+    char_vecs: Vec<(&[char], u64)>,
+    num_chars: usize,
+    rng: &mut ChaCha20Rng,
+) -> Vec<char> {
+    // Nothing can be drawn when every weight is zero, which the policy only allows when no
+    // characters are needed.
+    let Ok(index) = WeightedIndex::new(char_vecs.iter().map(|(_, weight)| *weight)) else {
+        return Vec::new();
+    };
+    let choices: Vec<Choose<char>> = char_vecs
+        .iter()
+        .map(|(chars, _)| Choose::new(chars).unwrap())
+        .collect();
+    (0..num_chars)
+        .map(|_| *choices[index.sample(rng)].sample(rng))
+        .collect()
+}
+
+pub fn generate_random_words_from_word_vec<'a>(
+    words: &[&'a str],
+    num_words: usize,
+    rng: &mut ChaCha20Rng,
+) -> Vec<&'a str> {
+    Choose::new(words)
+        .unwrap()
+        .sample_iter(rng)
+        .take(num_words)
+        .copied()
+        .collect()
+}
+
+fn ln_add(a: f64, b: f64) -> f64 {
+    match (a, b) {
+        (f64::NEG_INFINITY, _) => b,
+        (_, f64::NEG_INFINITY) => a,
+        _ => {
+            let (hi, lo) = if a > b { (a, b) } else { (b, a) };
+            hi + (lo - hi).exp().ln_1p()
+        }
+    }
+}
+
+// Splits `total` draws between buckets like a multinomial distribution with the given
+// (unnormalized) log probabilities, conditioned on no bucket exceeding its cap. This gives the
+// same distribution as rejection sampling without ever rejecting. Returns `None` when the caps
+// cannot hold `total` draws.
+pub fn sample_capped_multinomial(
+    log_weights: &[f64],
+    caps: &[usize],
+    total: usize,
+    rng: &mut ChaCha20Rng,
+) -> Option<Vec<usize>> {
+    let mut ln_fact = vec![0.0; total + 1];
+    for k in 1..=total {
+        ln_fact[k] = ln_fact[k - 1] + (k as f64).ln();
+    }
+    let term = |i: usize, j: usize| match (j, log_weights[i]) {
+        (0, _) => 0.0,
+        (_, f64::NEG_INFINITY) => f64::NEG_INFINITY,
+        (_, lw) => j as f64 * lw - ln_fact[j],
+    };
+
+    // suffix[i][t] is the log of the total mass of splitting t draws between buckets i...
+    let n = log_weights.len();
+    let mut suffix = vec![vec![f64::NEG_INFINITY; total + 1]; n + 1];
+    suffix[n][0] = 0.0;
+    for i in (0..n).rev() {
+        for t in 0..=total {
+            for j in 0..=caps[i].min(t) {
+                suffix[i][t] = ln_add(suffix[i][t], term(i, j) + suffix[i + 1][t - j]);
+            }
+        }
+    }
+    if suffix[0][total] == f64::NEG_INFINITY {
+        return None;
+    }
+
+    let mut remaining = total;
+    let mut counts = Vec::with_capacity(n);
+    for i in 0..n {
+        let weights: Vec<f64> = (0..=caps[i].min(remaining))
+            .map(|j| (term(i, j) + suffix[i + 1][remaining - j] - suffix[i][remaining]).exp())
+            .collect();
+        let j = WeightedIndex::new(&weights).unwrap().sample(rng);
+        counts.push(j);
+        remaining -= j;
+    }
+    Some(counts)
+}
+
 pub fn generate_rng() -> ChaCha20Rng {
     let mut seed = [0u8; 32];
     if let Err(e) = OsRng.try_fill_bytes(&mut seed) {
@@ -624,3 +715,70 @@ pub fn generate_rng() -> ChaCha20Rng {
 //         _ => "Undefined",
 //     })
 // }
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use super::sample_capped_multinomial;
+
+    #[test]
+    fn capped_multinomial_respects_caps_and_total() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let cases: [(&[f64], &[usize], usize); 5] = [
+            (&[0.0, 0.0, 0.0], &[1, 2, 3], 6),
+            (&[0.0, 5.0, -5.0], &[2, 1, 10], 7),
+            (&[2.0, 0.0], &[0, 40], 40),
+            (&[0.0, f64::NEG_INFINITY, 1.0], &[3, 100, 4], 5),
+            (&[10.0, 0.0, 0.0, 0.0], &[1, 50, 50, 50], 100),
+        ];
+        for (log_weights, caps, total) in cases {
+            for _ in 0..1000 {
+                let counts = sample_capped_multinomial(log_weights, caps, total, &mut rng).unwrap();
+                assert_eq!(counts.len(), caps.len());
+                assert_eq!(counts.iter().sum::<usize>(), total);
+                for (i, count) in counts.iter().enumerate() {
+                    assert!(count <= &caps[i], "{:?} exceeds {:?}", counts, caps);
+                    if log_weights[i] == f64::NEG_INFINITY {
+                        assert_eq!(*count, 0);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn capped_multinomial_fails_when_the_caps_cannot_hold_the_total() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        assert!(sample_capped_multinomial(&[0.0, 0.0], &[1, 2], 4, &mut rng).is_none());
+        assert!(
+            sample_capped_multinomial(&[0.0, f64::NEG_INFINITY], &[1, 5], 2, &mut rng).is_none()
+        );
+        assert_eq!(
+            sample_capped_multinomial(&[], &[], 0, &mut rng),
+            Some(Vec::new())
+        );
+    }
+
+    #[test]
+    fn capped_multinomial_matches_the_conditioned_distribution() {
+        // Three fair draws between two buckets, the first capped at one: (0, 3) has weight
+        // 1 / (0! 3!) and (1, 2) has weight 1 / (1! 2!), so (1, 2) comes up 3 times in 4.
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let rounds = 40_000;
+        let ones = (0..rounds)
+            .filter(|_| {
+                sample_capped_multinomial(&[0.0, 0.0], &[1, 3], 3, &mut rng) == Some(vec![1, 2])
+            })
+            .count();
+        // The count has a standard deviation of about 87, so this allows more than 5 of them.
+        let expected = rounds as f64 * 0.75;
+        assert!(
+            (ones as f64 - expected).abs() < 450.0,
+            "(1, 2) drawn {} times, expected about {}",
+            ones,
+            expected
+        );
+    }
+}
@@ -1,8 +1,19 @@
 mod app;
+mod cli;
 mod ui;
 
-use crate::ui::opening_ui;
+use std::process::ExitCode;
 
-fn main() {
-    opening_ui();
+use clap::Parser;
+
+use crate::{cli::Cli, ui::opening_ui};
+
+fn main() -> ExitCode {
+    match Cli::parse().command {
+        Some(command) => cli::run(command),
+        None => {
+            opening_ui();
+            ExitCode::SUCCESS
+        }
+    }
 }
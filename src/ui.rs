@@ -3,8 +3,24 @@ mod validation;
 use std::collections::HashSet;
 
 use crate::{
-    app::config::{Config, category::CodePointCategory},
-    ui::validation::validate_usize,
+    app::config::{
+        Config, GenerationMode, SamplingMode,
+        category::{CodePointCategory, PositionRule},
+        entropy::reaches_entropy,
+        exclusion::{ConfusableExclusion, UnicodeVersion},
+        hash::TargetHash,
+        length::{LengthUnit, get_length_summary},
+        normalization::NormalizationForm,
+        passphrase::{Capitalization, Wordlist},
+        password_rules::parse_password_rules,
+        policy::PolicyError,
+        preset::PRESETS,
+        regex::{MAX_REGEX_LENGTH, RegexAutomaton},
+        rules::{RejectionRules, keyboard::KeyboardLayout},
+        script::RestrictionLevel,
+        template::{TemplateError, TemplatePart, parse_template},
+    },
+    ui::validation::{validate_usize, validate_usize_or_zero},
 };
 
 #[allow(unused_imports)]
@@ -23,17 +39,54 @@ pub fn opening_ui() {
 
     spinner.stop("");
 
-    set_password_length_ui(&mut config);
+    match config.load() {
+        Ok(true) => log::info("Loaded saved configuration.").ok().unwrap(),
+        Ok(false) => {}
+        Err(e) => log::warning(format!("Could not load saved configuration: {}", e))
+            .ok()
+            .unwrap(),
+    }
+
+    if config.get_target_entropy().is_none()
+        && matches!(
+            config.get_generation_mode(),
+            GenerationMode::Characters | GenerationMode::Regex
+        )
+    {
+        set_password_length_ui(&mut config);
+    }
 
     main_menu_ui(&mut config);
 }
 
 fn set_password_length_ui(config: &mut Config) {
-    let minimum_length = config.get_min_required_chars_for_active_categories();
-    let password_length: usize = input(format!(
-        "Enter password length (minimum {} characters):",
-        minimum_length
-    ))
+    let unit = config.get_length_unit();
+    let (minimum_length, maximum_length, maximum_reason) = match config.get_generation_mode() {
+        GenerationMode::Regex => (
+            1,
+            Some(MAX_REGEX_LENGTH),
+            "the longest password supported in regex mode",
+        ),
+        // The category bounds count code points, so other units are checked by the policy.
+        _ if !unit.counts_code_points() => (1, None, ""),
+        _ => (
+            config.get_min_required_chars_for_active_categories(),
+            config.get_max_total_chars_for_active_categories(),
+            "the sum of the maximum allowed characters for all enabled categories",
+        ),
+    };
+    let prompt = match (maximum_length, unit.counts_code_points()) {
+        (Some(maximum), _) => format!(
+            "Enter password length (between {} and {} characters):",
+            minimum_length, maximum
+        ),
+        (None, true) => format!(
+            "Enter password length (minimum {} characters):",
+            minimum_length
+        ),
+        (None, false) => format!("Enter password length in {}:", unit.get_label()),
+    };
+    let password_length: usize = input(prompt)
     .placeholder(config.get_password_length().to_string().as_str())
     .validate(validate_usize)
     .validate(
@@ -45,15 +98,140 @@ fn set_password_length_ui(config: &mut Config) {
             false => Ok(()),
         },
     )
+    .validate(move |input: &String| match maximum_length {
+        Some(maximum) if input.parse::<usize>().ok().unwrap() > maximum => Err(format!(
+            "Password length must be at most {} characters, which is {}.",
+            maximum, maximum_reason
+        )),
+        _ => Ok(()),
+    })
     .interact()
     .ok()
     .unwrap();
 
-    config.set_password_length(password_length);
+    let previous_length = config.get_password_length();
+    let previous_target = config.get_target_entropy();
+    apply_policy_change_ui(
+        config,
+        |config| config.set_password_length(password_length),
+        |config| {
+            config.set_password_length(previous_length);
+            config.set_target_entropy(previous_target);
+        },
+    );
+    if previous_target.is_some() && config.get_target_entropy().is_none() {
+        log::info("Target entropy mode disabled in favour of a fixed password length.")
+            .ok()
+            .unwrap();
+    }
+}
+
+fn set_target_entropy_ui(config: &mut Config) {
+    let bits: f64 =
+        input("Enter the required entropy in bits (0 to go back to a fixed password length):")
+            .placeholder(
+                config
+                    .get_target_entropy()
+                    .unwrap_or(0.0)
+                    .to_string()
+                    .as_str(),
+            )
+            .validate(|input: &String| match input.parse::<f64>() {
+                Ok(bits) if bits.is_finite() && bits >= 0.0 => Ok(()),
+                _ => Err("Please enter a non-negative number of bits."),
+            })
+            .interact()
+            .ok()
+            .unwrap();
+
+    if bits == 0.0 {
+        config.set_target_entropy(None);
+        let message = match config.get_generation_mode() {
+            GenerationMode::Characters | GenerationMode::Regex => format!(
+                "Using a fixed password length of {} characters.",
+                config.get_password_length()
+            ),
+            GenerationMode::Passphrase => format!(
+                "Using a fixed passphrase of {} words.",
+                config.get_word_count()
+            ),
+            GenerationMode::Pronounceable => format!(
+                "Using a fixed pronounceable password of {} syllables.",
+                config.get_syllable_count()
+            ),
+            GenerationMode::Template => "The template decides the password length.".to_string(),
+        };
+        log::info(message).ok().unwrap();
+        return;
+    }
+
+    let derived = match config.get_generation_mode() {
+        GenerationMode::Characters => config.get_length_for_entropy(bits).map(|length| {
+            format!(
+                "Password length will be derived from the enabled characters, currently {} {}",
+                length,
+                config.get_length_unit().get_label()
+            )
+        }),
+        GenerationMode::Passphrase => config.get_word_count_for_entropy(bits).map(|count| {
+            format!(
+                "Word count will be derived from the passphrase options, currently {} words",
+                count
+            )
+        }),
+        GenerationMode::Pronounceable => config.get_syllable_count_for_entropy(bits).map(|count| {
+            format!(
+                "Syllable count will be derived from the pronounceable options, currently {} syllables",
+                count
+            )
+        }),
+        GenerationMode::Regex => config
+            .get_regex_automaton()
+            .ok()
+            .and_then(|automaton| automaton.get_length_for_entropy(bits))
+            .map(|length| {
+                format!(
+                    "Password length will be derived from the regex, currently {} characters",
+                    length
+                )
+            }),
+        GenerationMode::Template => reaches_entropy(config.get_template_entropy_bits(), bits)
+            .then(|| "The template will be checked against this target".to_string()),
+    };
+    match derived {
+        Some(message) => {
+            config.set_target_entropy(Some(bits));
+            log::success(format!("{} ({}).", message, config.get_entropy_label()))
+                .ok()
+                .unwrap();
+        }
+        None => log::error(
+            "The current settings cannot reach that entropy. Enable more characters or words and try again.",
+        )
+        .ok()
+        .unwrap(),
+    }
 }
 
 fn generate_password_ui(config: &mut Config) {
+    if let Err(errors) = config.validate_policy() {
+        log_policy_errors(&errors);
+        return;
+    }
+
     let password = config.generate_password();
+    log::info(format!(
+        "Entropy of the current configuration: {}",
+        config.get_entropy_label()
+    ))
+    .ok()
+    .unwrap();
+    log::info(format!("Length: {}", get_length_summary(&password)))
+        .ok()
+        .unwrap();
+    if let Some(warning) = config.get_hash_warning() {
+        log::warning(warning).ok().unwrap();
+    }
     let items: Vec<(usize, &str, &str)> = vec![
         (0, "Print", "Print the password to the console"),
         (
@@ -89,18 +267,32 @@ fn main_menu_ui(config: &mut Config) {
             "Edit Config",
             "Edit the configuration settings, including password length, character sets, and more",
         ),
-        (2, "Exit", "Exit"),
+        (
+            2,
+            "Profiles",
+            "Create, duplicate, rename, delete and switch between named profiles",
+        ),
+        (3, "Exit", "Exit"),
     ];
 
     loop {
-        let choice = select("Main Menu").items(&items).interact().ok().unwrap();
+        let choice = select(format!(
+            "Main Menu - Profile: {} - Entropy: {}",
+            config.get_active_profile_name(),
+            config.get_entropy_label()
+        ))
+        .items(&items)
+        .interact()
+        .ok()
+        .unwrap();
 
         match choice {
             0 => {
                 generate_password_ui(config);
             }
             1 => edit_config_ui(config),
-            2 => {
+            2 => profiles_ui(config),
+            3 => {
                 outro("Thank you for using Passgen!").ok().unwrap();
                 break;
             }
@@ -109,150 +301,1385 @@ fn main_menu_ui(config: &mut Config) {
     }
 }
 
+fn select_profile_ui(config: &Config, prompt: &str) -> Option<String> {
+    let mut items: Vec<(usize, String, &str)> = config
+        .get_profile_names()
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (i, name.to_string(), ""))
+        .collect();
+    items.push((
+        items.len(),
+        "<- Back".to_string(),
+        "Return to the previous menu",
+    ));
+
+    let choice = select(prompt).items(&items).interact().ok().unwrap();
+    (choice < items.len() - 1).then(|| items[choice].1.clone())
+}
+
+fn profile_name_ui(prompt: &str) -> String {
+    input(prompt).interact().ok().unwrap()
+}
+
+fn profiles_ui(config: &mut Config) {
+    let items = vec![
+        (0, "Switch Profile", "Make another profile the active one"),
+        (
+            1,
+            "Create Profile",
+            "Create a new profile with the default settings",
+        ),
+        (
+            2,
+            "Duplicate Profile",
+            "Create a new profile from the active profile's settings",
+        ),
+        (3, "Rename Profile", "Rename the active profile"),
+        (4, "Delete Profile", "Delete a profile"),
+        (5, "<- Back", "Return to the previous menu"),
+    ];
+
+    loop {
+        let choice = select(format!(
+            "Profiles - Active: {} (use Edit Config > Save to keep changes)",
+            config.get_active_profile_name()
+        ))
+        .items(&items)
+        .interact()
+        .ok()
+        .unwrap();
+
+        let result = match choice {
+            0 => match select_profile_ui(config, "Switch to profile") {
+                Some(name) => config.switch_profile(&name),
+                None => continue,
+            },
+            1 => config.create_profile(&profile_name_ui("Name of the new profile:")),
+            2 => config.duplicate_profile(&profile_name_ui("Name of the duplicated profile:")),
+            3 => config.rename_profile(&profile_name_ui("New name for the active profile:")),
+            4 => match select_profile_ui(config, "Delete profile") {
+                Some(name) => config.delete_profile(&name),
+                None => continue,
+            },
+            5 => break,
+            _ => continue,
+        };
+
+        match result {
+            Ok(()) => log::success(format!(
+                "Active profile: {}",
+                config.get_active_profile_name()
+            ))
+            .ok()
+            .unwrap(),
+            Err(e) => log::error(format!("{}", e)).ok().unwrap(),
+        }
+    }
+}
+
 fn edit_config_ui(config: &mut Config) {
     let items = vec![
         (
             0,
+            "Generation Mode",
+            "Choose between random characters, a passphrase of random words, pronounceable syllables, a template and a regex",
+        ),
+        (
+            1,
             "Password Length",
             "Set the length of the generated password",
         ),
         (
-            1,
+            2,
+            "Target Entropy",
+            "Derive the password length or word count from a required entropy in bits",
+        ),
+        (
+            3,
+            "Sampling Mode",
+            "Choose how the characters beyond each category's minimum are picked",
+        ),
+        (
+            4,
             "Character Sets",
             "Enable/disable characters and categories, set minimum characters per category, and so on",
         ),
-        (2, "Back to Main Menu", "Return to the main menu"),
+        (
+            5,
+            "Passphrase Options",
+            "Set the wordlist, word count, separator, capitalization and digits of passphrases",
+        ),
+        (
+            6,
+            "Pronounceable Options",
+            "Set the syllable count and the extra characters of pronounceable passwords",
+        ),
+        (
+            7,
+            "Template",
+            "Set the template used by the template generation mode",
+        ),
+        (
+            8,
+            "Regex",
+            "Set the regular expression used by the regex generation mode",
+        ),
+        (
+            9,
+            "Rejection Rules",
+            "Reject passwords with repeated characters, sequences or keyboard runs",
+        ),
+        (
+            10,
+            "Presets",
+            "Replace the settings with a vendor or compliance policy such as AWS IAM or WPA2",
+        ),
+        (
+            11,
+            "Import Password Rules",
+            "Replace the settings with ones that follow a passwordrules policy string",
+        ),
+        (
+            12,
+            "Export Password Rules",
+            "Show the current settings as a passwordrules policy string",
+        ),
+        (
+            13,
+            "Character Filters",
+            "Exclude confusable, newer, unnormalized or case-sensitive characters and limit which scripts a password may mix",
+        ),
+        (
+            14,
+            "Length Unit",
+            "Count the password length in code points, UTF-8 bytes, UTF-16 units or grapheme clusters",
+        ),
+        (
+            15,
+            "Target Hash",
+            "Warn when the password hash on the server ignores part of the password",
+        ),
+        (16, "Save", "Save the current configuration to disk"),
+        (17, "Back to Main Menu", "Return to the main menu"),
     ];
 
     loop {
-        let choice = select("Edit Config").items(&items).interact().ok().unwrap();
+        let choice = select(format!(
+            "Edit Config - Mode: {}",
+            config.get_generation_mode().get_label()
+        ))
+        .items(&items)
+        .interact()
+        .ok()
+        .unwrap();
 
         match choice {
-            0 => set_password_length_ui(config),
-            1 => edit_categories_ui(config),
-            2 => break,
+            0 => set_generation_mode_ui(config),
+            1 => set_password_length_ui(config),
+            2 => set_target_entropy_ui(config),
+            3 => set_sampling_mode_ui(config),
+            4 => edit_categories_ui(config),
+            5 => edit_passphrase_options_ui(config),
+            6 => edit_pronounceable_options_ui(config),
+            7 => set_template_ui(config),
+            8 => set_regex_ui(config),
+            9 => edit_rejection_rules_ui(config),
+            10 => select_preset_ui(config),
+            11 => import_password_rules_ui(config),
+            12 => export_password_rules_ui(config),
+            13 => edit_character_filters_ui(config),
+            14 => set_length_unit_ui(config),
+            15 => set_target_hash_ui(config),
+            16 => save_config_ui(config),
+            17 => break,
             _ => continue,
         }
     }
 }
 
-fn edit_categories_ui(config: &mut Config) {
+fn set_length_unit_ui(config: &mut Config) {
+    let items = vec![
+        (
+            LengthUnit::CodePoints,
+            LengthUnit::CodePoints.get_label(),
+            "Unicode scalar values, as most password rules count characters",
+        ),
+        (
+            LengthUnit::Utf8Bytes,
+            LengthUnit::Utf8Bytes.get_label(),
+            "For limits such as the 72 bytes bcrypt reads",
+        ),
+        (
+            LengthUnit::Utf16Units,
+            LengthUnit::Utf16Units.get_label(),
+            "For Windows and Active Directory limits",
+        ),
+        (
+            LengthUnit::Graphemes,
+            LengthUnit::Graphemes.get_label(),
+            "The characters a user sees and types",
+        ),
+    ];
+
+    let previous = config.get_length_unit();
+    let unit = select("Length Unit")
+        .items(&items)
+        .initial_value(previous)
+        .interact()
+        .ok()
+        .unwrap();
+
+    apply_policy_change_ui(
+        config,
+        |config| config.set_length_unit(unit),
+        |config| config.set_length_unit(previous),
+    );
+}
+
+fn set_target_hash_ui(config: &mut Config) {
+    let items = vec![
+        (
+            TargetHash::None,
+            TargetHash::None.get_label(),
+            "Do not check the password against a hash",
+        ),
+        (
+            TargetHash::Bcrypt,
+            TargetHash::Bcrypt.get_label(),
+            "Reads at most 72 UTF-8 bytes",
+        ),
+        (
+            TargetHash::Scrypt,
+            TargetHash::Scrypt.get_label(),
+            "Reads the whole password",
+        ),
+        (
+            TargetHash::Argon2,
+            TargetHash::Argon2.get_label(),
+            "Reads the whole password",
+        ),
+        (
+            TargetHash::Pbkdf2,
+            TargetHash::Pbkdf2.get_label(),
+            "Reads the whole password",
+        ),
+        (
+            TargetHash::Ntlm,
+            TargetHash::Ntlm.get_label(),
+            "Windows reads at most 256 UTF-16 code units",
+        ),
+    ];
+
+    let hash = select("Target Hash")
+        .items(&items)
+        .initial_value(config.get_target_hash())
+        .interact()
+        .ok()
+        .unwrap();
+    config.set_target_hash(hash);
+
+    let (Some(warning), Some((unit, limit))) = (config.get_hash_warning(), hash.get_limit()) else {
+        return;
+    };
+    log::warning(warning).ok().unwrap();
+    if config.get_generation_mode() != GenerationMode::Characters {
+        return;
+    }
+
+    let confirmed = confirm(format!(
+        "Count the password length in {} and set it to {}?",
+        unit.get_label(),
+        limit
+    ))
+    .interact()
+    .ok()
+    .unwrap();
+    if !confirmed {
+        return;
+    }
+    let previous_unit = config.get_length_unit();
+    let previous_length = config.get_password_length();
+    let previous_target = config.get_target_entropy();
+    apply_policy_change_ui(
+        config,
+        |config| {
+            config.set_length_unit(unit);
+            config.set_password_length(limit);
+        },
+        |config| {
+            config.set_length_unit(previous_unit);
+            config.set_password_length(previous_length);
+            config.set_target_entropy(previous_target);
+        },
+    );
+    if config.get_length_unit() == unit && config.get_password_length() == limit {
+        log::success(format!(
+            "Passwords are now {} {} long ({}).",
+            limit,
+            unit.get_label(),
+            config.get_entropy_label()
+        ))
+        .ok()
+        .unwrap();
+    }
+}
+
+fn set_generation_mode_ui(config: &mut Config) {
+    let items = vec![
+        (
+            GenerationMode::Characters,
+            GenerationMode::Characters.get_label(),
+            "Random code points from the enabled categories",
+        ),
+        (
+            GenerationMode::Passphrase,
+            GenerationMode::Passphrase.get_label(),
+            "Random words from a wordlist, Diceware style",
+        ),
+        (
+            GenerationMode::Pronounceable,
+            GenerationMode::Pronounceable.get_label(),
+            "Random syllables that are easy to read aloud",
+        ),
+        (
+            GenerationMode::Template,
+            GenerationMode::Template.get_label(),
+            "Literal text and placeholders such as {ASCII Digits:4}",
+        ),
+        (
+            GenerationMode::Regex,
+            GenerationMode::Regex.get_label(),
+            "Strings of the password length that fully match a regex",
+        ),
+    ];
+
+    let previous_mode = config.get_generation_mode();
+    let mode = select("Generation Mode")
+        .items(&items)
+        .initial_value(previous_mode)
+        .interact()
+        .ok()
+        .unwrap();
+
+    apply_policy_change_ui(
+        config,
+        |config| config.set_generation_mode(mode),
+        |config| config.set_generation_mode(previous_mode),
+    );
+}
+
+fn edit_passphrase_options_ui(config: &mut Config) {
     loop {
-        let mut items: Vec<(usize, String, String)> = config
-            .get_all_categories()
-            .iter()
-            .enumerate()
-            .map(|(i, cat)| (i, cat.get_label(), cat.get_hint()))
-            .collect();
+        let options = config.get_passphrase_options();
+        let separator = match &options.separator_category {
+            Some(name) => format!("Random from {}", name),
+            None => format!("\"{}\"", options.separator),
+        };
+        let items = vec![
+            (0, format!("Wordlist: {}", options.wordlist.get_label())),
+            (1, format!("Word Count: {}", config.get_word_count())),
+            (2, format!("Separator: {}", separator)),
+            (
+                3,
+                format!("Capitalization: {}", options.capitalization.get_label()),
+            ),
+            (4, format!("Digits: {}", options.digits)),
+            (5, "<- Back".to_string()),
+        ];
+        let items: Vec<(usize, String, &str)> =
+            items.into_iter().map(|(i, label)| (i, label, "")).collect();
 
-        items.push((
-            items.len(),
-            "<- Back".to_string(),
-            "Return to the previous menu".to_string(),
-        ));
-        let choice = select("Edit Categories")
-            .items(&items)
-            .interact()
-            .ok()
-            .unwrap();
+        let choice = select(format!(
+            "Passphrase Options - Entropy: {}",
+            config.get_entropy_label()
+        ))
+        .items(&items)
+        .interact()
+        .ok()
+        .unwrap();
 
         match choice {
-            i if i < items.len() - 1 => {
-                edit_single_category_ui(config, i);
-            }
-            i if i == items.len() - 1 => {
-                break; // Back to previous menu
-            }
+            0 => set_wordlist_ui(config),
+            1 => set_word_count_ui(config),
+            2 => set_separator_ui(config),
+            3 => set_capitalization_ui(config),
+            4 => set_digits_ui(config),
+            5 => break,
             _ => continue,
         }
     }
 }
 
-fn edit_single_category_ui(config: &mut Config, cat_idx: usize) {
+fn edit_pronounceable_options_ui(config: &mut Config) {
     loop {
-        // Copy the values needed for validation to avoid borrowing issues
-        let password_length = config.get_password_length();
-        let total_min_required = config.get_min_required_chars_for_active_categories();
-
-        if let Some(cat) = config.categories.get_mut(cat_idx) {
-            let items = vec![
-                (0, cat.get_toggle_label(), ""),
-                (
-                    1,
-                    "Set Minimum Characters",
-                    "Set the minimum number of characters required from this category",
-                ),
-                (
-                    2,
-                    "Toggle Individual Code Pages",
-                    "Enable or disable individual code pages within this category",
+        let extras = config
+            .get_pronounceable_options()
+            .extra_characters
+            .iter()
+            .map(|extra| format!("{} {}", extra.count, extra.category))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let items: Vec<(usize, String, &str)> = vec![
+            (
+                0,
+                format!("Syllable Count: {}", config.get_syllable_count()),
+                "",
+            ),
+            (
+                1,
+                format!(
+                    "Extra Characters: {}",
+                    match extras.is_empty() {
+                        true => "None".to_string(),
+                        false => extras,
+                    }
                 ),
-                (3, "<- Back", "Return to the previous menu"),
-            ];
+                "Characters from other categories, such as digits or symbols, placed between syllables",
+            ),
+            (2, "<- Back".to_string(), ""),
+        ];
 
-            let choice = select(format!("Edit {}", cat.get_label()))
-                .items(&items)
-                .interact()
-                .ok()
-                .unwrap();
+        let choice = select(format!(
+            "Pronounceable Options - Entropy: {}",
+            config.get_entropy_label()
+        ))
+        .items(&items)
+        .interact()
+        .ok()
+        .unwrap();
 
-            match choice {
-                0 => {
-                    if !cat.is_enabled()
-                        && password_length < total_min_required + cat.get_min_required_chars()
-                    {
-                        log::error(format!(
-                            "Enabling this category requires a password length of at least {} characters.",
-                            total_min_required
-                        ))
-                        .ok()
-                        .unwrap();
-                    } else {
-                        cat.toggle_enabled();
-                    }
-                }
-                1 => edit_min_characters_for_category_ui(cat, password_length, total_min_required),
-                2 => toggle_code_pages_ui(config, cat_idx),
-                3 => break, // Back to previous menu
-                _ => continue,
-            }
+        match choice {
+            0 => set_syllable_count_ui(config),
+            1 => set_extra_characters_ui(config),
+            2 => break,
+            _ => continue,
         }
     }
 }
 
-fn edit_min_characters_for_category_ui(
-    cat: &mut CodePointCategory,
-    password_length: usize,
-    total_min_required: usize,
-) {
-    let max_allowed_for_cat =
-        match password_length < total_min_required - cat.get_min_required_chars() {
-            true => 0,
-            false => password_length - total_min_required + cat.get_min_required_chars(),
-        };
-    let is_category_enabled = cat.is_enabled();
+fn set_syllable_count_ui(config: &mut Config) {
+    let syllable_count: usize = input("Enter the number of syllables:")
+        .placeholder(config.get_syllable_count().to_string().as_str())
+        .validate(validate_usize)
+        .interact()
+        .ok()
+        .unwrap();
+    config.set_syllable_count(syllable_count);
+}
 
-    let min_chars: usize = input(format!(
-        "Enter minimum required length for this category (between 0 and {} characters):",
-        max_allowed_for_cat,
-    ))
-    .placeholder(cat.get_min_required_chars().to_string().as_str())
-    .validate(validate_usize)
-    .validate({
-        move |input: &String| match input.parse::<usize>().ok().unwrap() <= max_allowed_for_cat
-            && is_category_enabled
-        {
-            true => Ok(()),
-            false => Err(format!(
-                "Value must be between 0 and {}",
-                max_allowed_for_cat
-            )),
-        }
-    })
-    .interact()
+fn set_extra_characters_ui(config: &mut Config) {
+    let items: Vec<(usize, &str, &str)> = config
+        .get_all_categories()
+        .iter()
+        .enumerate()
+        .map(|(i, cat)| (i, cat.get_name(), ""))
+        .collect();
+    let cat_idx = select("Draw extra characters from")
+        .items(&items)
+        .interact()
+        .ok()
+        .unwrap();
+    let name = config.get_all_categories()[cat_idx].get_name().to_string();
+
+    let count: usize = input("Enter the number of characters from this category (0 for none):")
+        .validate(validate_usize_or_zero)
+        .interact()
+        .ok()
+        .unwrap();
+
+    let previous = config.get_pronounceable_options().extra_characters.clone();
+    apply_policy_change_ui(
+        config,
+        |config| config.set_extra_characters(&name, count),
+        |config| config.get_pronounceable_options_mut().extra_characters = previous,
+    );
+}
+
+fn set_template_ui(config: &mut Config) {
+    log::info(
+        "{Name} draws one character from a category, {Name:4} draws four and {Name|Other Name:4} draws from both. Anything else is literal text; use {{ and }} for braces.",
+    )
+    .ok()
+    .unwrap();
+    let category_names: Vec<String> = config
+        .get_all_categories()
+        .iter()
+        .map(|cat| cat.get_name().to_string())
+        .collect();
+    let template: String = input("Enter the template:")
+        .default_input(config.get_template())
+        .validate(move |input: &String| {
+            parse_template(input)
+                .map_err(|e| e.to_string())?
+                .iter()
+                .flat_map(|part| match part {
+                    TemplatePart::Placeholder { categories, .. } => categories.clone(),
+                    TemplatePart::Literal(_) => Vec::new(),
+                })
+                .find(|name| {
+                    !category_names
+                        .iter()
+                        .any(|cat| cat.eq_ignore_ascii_case(name))
+                })
+                .map_or(Ok(()), |name| {
+                    Err(TemplateError::UnknownCategory(name).to_string())
+                })
+        })
+        .interact()
+        .ok()
+        .unwrap();
+
+    let previous = config.get_template().to_string();
+    apply_policy_change_ui(
+        config,
+        |config| config.set_template(&template),
+        |config| config.set_template(&previous),
+    );
+}
+
+fn set_regex_ui(config: &mut Config) {
+    log::info(
+        "The whole password must match. Character classes only match enabled code points, while literal characters are always allowed.",
+    )
     .ok()
     .unwrap();
-    cat.set_min_required_chars(min_chars);
+    let regex: String = input("Enter the regex:")
+        .default_input(config.get_regex())
+        .validate(|input: &String| match RegexAutomaton::new(input, &[]) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        })
+        .interact()
+        .ok()
+        .unwrap();
+
+    let previous = config.get_regex().to_string();
+    apply_policy_change_ui(
+        config,
+        |config| config.set_regex(&regex),
+        |config| config.set_regex(&previous),
+    );
+}
+
+fn edit_rejection_rules_ui(config: &mut Config) {
+    loop {
+        let rules = config.get_rejection_rules_mut().clone();
+        let limit = |max: Option<usize>| max.map_or("No limit".to_string(), |max| max.to_string());
+        let items: Vec<(usize, String, &str)> = vec![
+            (
+                0,
+                format!(
+                    "Max Consecutive Characters: {}",
+                    limit(rules.max_consecutive)
+                ),
+                "Identical characters in a row, such as \"aaa\"",
+            ),
+            (
+                1,
+                format!("Max Sequence: {}", limit(rules.max_sequence)),
+                "Ascending or descending code points in a row, such as \"1234\" or \"dcba\"",
+            ),
+            (
+                2,
+                format!("Max Keyboard Run: {}", limit(rules.max_keyboard_run)),
+                "Adjacent keys in a row, such as \"qwerty\" or \"1qaz\"",
+            ),
+            (
+                3,
+                format!("Keyboard Layout: {}", rules.keyboard_layout.get_label()),
+                "The layout used to find keyboard runs",
+            ),
+            (4, "<- Back".to_string(), ""),
+        ];
+
+        let choice = select(format!(
+            "Rejection Rules - Entropy: {}",
+            config.get_entropy_label()
+        ))
+        .items(&items)
+        .interact()
+        .ok()
+        .unwrap();
+
+        match choice {
+            0 => set_rule_limit_ui(
+                config,
+                "Enter the most times a character may repeat in a row (empty for no limit):",
+                |rules| &mut rules.max_consecutive,
+            ),
+            1 => set_rule_limit_ui(
+                config,
+                "Enter the longest allowed run of ascending or descending characters (empty for no limit):",
+                |rules| &mut rules.max_sequence,
+            ),
+            2 => set_rule_limit_ui(
+                config,
+                "Enter the longest allowed run of adjacent keys (empty for no limit):",
+                |rules| &mut rules.max_keyboard_run,
+            ),
+            3 => set_keyboard_layout_ui(config),
+            4 => break,
+            _ => continue,
+        }
+    }
+}
+
+fn set_rule_limit_ui(
+    config: &mut Config,
+    prompt: &str,
+    limit: fn(&mut RejectionRules) -> &mut Option<usize>,
+) {
+    let previous = *limit(config.get_rejection_rules_mut());
+    let max: String = input(prompt)
+        .placeholder(
+            previous
+                .map_or("No limit".to_string(), |max| max.to_string())
+                .as_str(),
+        )
+        .required(false)
+        .validate(|input: &String| match input.trim() {
+            "" => Ok(()),
+            input => validate_usize(&input.to_string()),
+        })
+        .interact()
+        .ok()
+        .unwrap();
+    let max = max.trim().parse::<usize>().ok();
+
+    apply_policy_change_ui(
+        config,
+        |config| *limit(config.get_rejection_rules_mut()) = max,
+        |config| *limit(config.get_rejection_rules_mut()) = previous,
+    );
+}
+
+fn set_keyboard_layout_ui(config: &mut Config) {
+    let items: Vec<(KeyboardLayout, &str, &str)> = [
+        KeyboardLayout::Qwerty,
+        KeyboardLayout::Qwertz,
+        KeyboardLayout::Azerty,
+        KeyboardLayout::Dvorak,
+    ]
+    .into_iter()
+    .map(|layout| (layout, layout.get_label(), ""))
+    .collect();
+    config.get_rejection_rules_mut().keyboard_layout = select("Keyboard Layout")
+        .items(&items)
+        .initial_value(config.get_rejection_rules_mut().keyboard_layout)
+        .interact()
+        .ok()
+        .unwrap();
+}
+
+fn select_preset_ui(config: &mut Config) {
+    let mut items: Vec<(usize, &str, &str)> = PRESETS
+        .iter()
+        .enumerate()
+        .map(|(i, preset)| (i, preset.name, preset.description))
+        .collect();
+    items.push((PRESETS.len(), "Back", "Return to the previous menu"));
+
+    let choice = select("Presets").items(&items).interact().ok().unwrap();
+    let Some(preset) = PRESETS.get(choice) else {
+        return;
+    };
+
+    let confirmed = confirm("This replaces the current settings of the active profile. Continue?")
+        .interact()
+        .ok()
+        .unwrap();
+    if !confirmed {
+        return;
+    }
+
+    match config.apply_preset(preset) {
+        Ok(warnings) => {
+            for warning in warnings {
+                log::warning(warning).ok().unwrap();
+            }
+            log::success(format!("Applied the {} preset.", preset.name))
+                .ok()
+                .unwrap();
+        }
+        Err(e) => log::error(format!("{}", e)).ok().unwrap(),
+    }
+}
+
+fn import_password_rules_ui(config: &mut Config) {
+    let rules: String = input("Enter the password rules:")
+        .placeholder("minlength: 20; required: lower; required: digit; max-consecutive: 2;")
+        .validate(|input: &String| match parse_password_rules(input) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        })
+        .interact()
+        .ok()
+        .unwrap();
+
+    let confirmed = confirm("This replaces the current settings of the active profile. Continue?")
+        .interact()
+        .ok()
+        .unwrap();
+    if !confirmed {
+        return;
+    }
+
+    match config.apply_password_rules(&rules) {
+        Ok(warnings) => {
+            for warning in warnings {
+                log::warning(warning).ok().unwrap();
+            }
+            if let Err(errors) = config.validate_policy() {
+                log_policy_errors(&errors);
+            }
+            log::success("Imported the password rules.").ok().unwrap();
+        }
+        Err(e) => log::error(format!("{}", e)).ok().unwrap(),
+    }
+}
+
+fn export_password_rules_ui(config: &Config) {
+    match config.to_password_rules() {
+        Ok(rules) => note("Password Rules", rules).ok().unwrap(),
+        Err(e) => log::error(format!("{}", e)).ok().unwrap(),
+    }
+}
+
+fn edit_character_filters_ui(config: &mut Config) {
+    loop {
+        let items: Vec<(usize, String, &str)> = vec![
+            (
+                0,
+                format!(
+                    "Confusable Characters: {}",
+                    config.get_confusable_exclusion().get_label()
+                ),
+                "Exclude characters that are easily mistaken for others",
+            ),
+            (
+                1,
+                format!(
+                    "Script Restriction: {}",
+                    config.get_restriction_level().get_label()
+                ),
+                "Limit which scripts a single password may mix",
+            ),
+            (
+                2,
+                format!(
+                    "Maximum Unicode Version: {}",
+                    config
+                        .get_max_unicode_version()
+                        .map_or("Any", |version| version.get_label())
+                ),
+                "Exclude characters assigned in later Unicode versions",
+            ),
+            (
+                3,
+                format!(
+                    "Normalization: {}",
+                    config.get_normalization_form().get_label()
+                ),
+                "Only generate passwords that the server's normalization leaves unchanged",
+            ),
+            (
+                4,
+                format!(
+                    "Case-Insensitive Safe: {}",
+                    match config.is_case_fold_safe() {
+                        true => "On",
+                        false => "Off",
+                    }
+                ),
+                "Exclude characters that lowercasing or case folding would change",
+            ),
+            (5, "<- Back".to_string(), ""),
+        ];
+
+        let choice = select(format!(
+            "Character Filters - Entropy: {}",
+            config.get_entropy_label()
+        ))
+        .items(&items)
+        .interact()
+        .ok()
+        .unwrap();
+
+        match choice {
+            0 => set_confusable_exclusion_ui(config),
+            1 => set_restriction_level_ui(config),
+            2 => set_max_unicode_version_ui(config),
+            3 => set_normalization_form_ui(config),
+            4 => toggle_case_fold_safe_ui(config),
+            5 => break,
+            _ => continue,
+        }
+    }
+}
+
+fn set_confusable_exclusion_ui(config: &mut Config) {
+    let items = vec![
+        (
+            ConfusableExclusion::None,
+            ConfusableExclusion::None.get_label(),
+            "Keep every enabled character",
+        ),
+        (
+            ConfusableExclusion::AsciiAmbiguous,
+            ConfusableExclusion::AsciiAmbiguous.get_label(),
+            "Exclude 0 O o 1 l I | ` ' \" for ASCII-only configurations",
+        ),
+        (
+            ConfusableExclusion::Uts39,
+            ConfusableExclusion::Uts39.get_label(),
+            "Exclude every character that Unicode lists as confusable with another, across scripts",
+        ),
+    ];
+
+    let previous = config.get_confusable_exclusion();
+    let exclusion = select("Confusable Characters")
+        .items(&items)
+        .initial_value(previous)
+        .interact()
+        .ok()
+        .unwrap();
+
+    apply_policy_change_ui(
+        config,
+        |config| config.set_confusable_exclusion(exclusion),
+        |config| config.set_confusable_exclusion(previous),
+    );
+}
+
+fn set_max_unicode_version_ui(config: &mut Config) {
+    let mut items = vec![(None, "Any", "Keep characters from every Unicode version")];
+    items.extend(UnicodeVersion::all().map(|version| (Some(version), version.get_label(), "")));
+
+    let previous = config.get_max_unicode_version();
+    let version = select("Maximum Unicode Version")
+        .items(&items)
+        .initial_value(previous)
+        .interact()
+        .ok()
+        .unwrap();
+
+    apply_policy_change_ui(
+        config,
+        |config| config.set_max_unicode_version(version),
+        |config| config.set_max_unicode_version(previous),
+    );
+}
+
+fn toggle_case_fold_safe_ui(config: &mut Config) {
+    let safe = !config.is_case_fold_safe();
+    apply_policy_change_ui(
+        config,
+        |config| config.set_case_fold_safe(safe),
+        |config| config.set_case_fold_safe(!safe),
+    );
+    if safe && config.is_case_fold_safe() {
+        log::warning(
+            "Uppercase and titlecase letters, and characters such as ß, ſ, ς and the Turkish dotless ı, are no longer generated, which lowers the entropy. Passphrase and template capitalization will be rejected.",
+        )
+        .ok()
+        .unwrap();
+    }
+}
+
+fn set_normalization_form_ui(config: &mut Config) {
+    let items = vec![
+        (
+            NormalizationForm::None,
+            NormalizationForm::None.get_label(),
+            "The server stores passwords as they are typed",
+        ),
+        (
+            NormalizationForm::Nfc,
+            NormalizationForm::Nfc.get_label(),
+            "Canonical composition, as most systems apply",
+        ),
+        (
+            NormalizationForm::Nfkc,
+            NormalizationForm::Nfkc.get_label(),
+            "Compatibility composition, as SASLprep applies",
+        ),
+        (
+            NormalizationForm::PrecisOpaqueString,
+            NormalizationForm::PrecisOpaqueString.get_label(),
+            "RFC 8265 passwords: NFC, spaces mapped, controls and ignorables rejected",
+        ),
+    ];
+
+    let previous = config.get_normalization_form();
+    let form = select("Normalization")
+        .items(&items)
+        .initial_value(previous)
+        .interact()
+        .ok()
+        .unwrap();
+
+    apply_policy_change_ui(
+        config,
+        |config| config.set_normalization_form(form),
+        |config| config.set_normalization_form(previous),
+    );
+}
+
+fn set_restriction_level_ui(config: &mut Config) {
+    let items = vec![
+        (
+            RestrictionLevel::Unrestricted,
+            RestrictionLevel::Unrestricted.get_label(),
+            "Mix the enabled characters of any scripts",
+        ),
+        (
+            RestrictionLevel::AsciiOnly,
+            RestrictionLevel::AsciiOnly.get_label(),
+            "Only use ASCII characters",
+        ),
+        (
+            RestrictionLevel::SingleScript,
+            RestrictionLevel::SingleScript.get_label(),
+            "Pick one script per password, plus common characters such as digits",
+        ),
+        (
+            RestrictionLevel::HighlyRestrictive,
+            RestrictionLevel::HighlyRestrictive.get_label(),
+            "Also allow Latin with Han and Japanese kana, Bopomofo or Hangul",
+        ),
+        (
+            RestrictionLevel::ModeratelyRestrictive,
+            RestrictionLevel::ModeratelyRestrictive.get_label(),
+            "Also allow Latin with any one script other than Cyrillic or Greek",
+        ),
+    ];
+
+    let previous = config.get_restriction_level();
+    let level = select("Script Restriction")
+        .items(&items)
+        .initial_value(previous)
+        .interact()
+        .ok()
+        .unwrap();
+
+    apply_policy_change_ui(
+        config,
+        |config| config.set_restriction_level(level),
+        |config| config.set_restriction_level(previous),
+    );
+}
+
+fn set_wordlist_ui(config: &mut Config) {
+    let items = vec![
+        (
+            Wordlist::EffLarge,
+            Wordlist::EffLarge.get_label(),
+            "7776 words, about 12.9 bits per word",
+        ),
+        (
+            Wordlist::EffShort,
+            Wordlist::EffShort.get_label(),
+            "1296 shorter words, about 10.3 bits per word",
+        ),
+    ];
+    config.get_passphrase_options_mut().wordlist = select("Wordlist")
+        .items(&items)
+        .initial_value(config.get_passphrase_options().wordlist)
+        .interact()
+        .ok()
+        .unwrap();
+}
+
+fn set_word_count_ui(config: &mut Config) {
+    let word_count: usize = input("Enter the number of words:")
+        .placeholder(config.get_word_count().to_string().as_str())
+        .validate(validate_usize)
+        .interact()
+        .ok()
+        .unwrap();
+    config.set_word_count(word_count);
+}
+
+fn set_separator_ui(config: &mut Config) {
+    let mut items: Vec<(usize, String, &str)> = vec![(
+        0,
+        "Fixed Separator".to_string(),
+        "The same text between every word",
+    )];
+    items.extend(
+        config
+            .get_all_categories()
+            .iter()
+            .enumerate()
+            .map(|(i, cat)| (i + 1, cat.get_name().to_string(), "")),
+    );
+
+    let choice = select("Draw separators from")
+        .items(&items)
+        .interact()
+        .ok()
+        .unwrap();
+    let previous = config.get_passphrase_options().separator_category.clone();
+    if choice == 0 {
+        let separator: String = input("Enter the separator (empty for none):")
+            .placeholder(config.get_passphrase_options().separator.as_str())
+            .required(false)
+            .interact()
+            .ok()
+            .unwrap();
+        let options = config.get_passphrase_options_mut();
+        options.separator = separator;
+        options.separator_category = None;
+        return;
+    }
+
+    let name = config.get_all_categories()[choice - 1]
+        .get_name()
+        .to_string();
+    apply_policy_change_ui(
+        config,
+        |config| config.get_passphrase_options_mut().separator_category = Some(name),
+        |config| config.get_passphrase_options_mut().separator_category = previous,
+    );
+}
+
+fn set_capitalization_ui(config: &mut Config) {
+    let items: Vec<(Capitalization, &str, &str)> = [
+        Capitalization::Lowercase,
+        Capitalization::Words,
+        Capitalization::Random,
+        Capitalization::Uppercase,
+    ]
+    .into_iter()
+    .map(|capitalization| (capitalization, capitalization.get_label(), ""))
+    .collect();
+    config.get_passphrase_options_mut().capitalization = select("Capitalization")
+        .items(&items)
+        .initial_value(config.get_passphrase_options().capitalization)
+        .interact()
+        .ok()
+        .unwrap();
+}
+
+fn set_digits_ui(config: &mut Config) {
+    let digits: usize = input("Enter the number of random digits to append to one of the words:")
+        .placeholder(config.get_passphrase_options().digits.to_string().as_str())
+        .validate(validate_usize_or_zero)
+        .interact()
+        .ok()
+        .unwrap();
+    config.get_passphrase_options_mut().digits = digits;
+}
+
+fn set_sampling_mode_ui(config: &mut Config) {
+    let items = vec![
+        (
+            SamplingMode::Uniform,
+            SamplingMode::Uniform.get_label(),
+            "Every enabled code point is equally likely",
+        ),
+        (
+            SamplingMode::Weighted,
+            SamplingMode::Weighted.get_label(),
+            "Pick a category by its weight, then a code point within it",
+        ),
+    ];
+
+    let previous_mode = config.get_sampling_mode();
+    let mode = select(format!(
+        "Sampling Mode - Current: {}",
+        previous_mode.get_label()
+    ))
+    .items(&items)
+    .initial_value(previous_mode)
+    .interact()
+    .ok()
+    .unwrap();
+
+    apply_policy_change_ui(
+        config,
+        |config| config.set_sampling_mode(mode),
+        |config| config.set_sampling_mode(previous_mode),
+    );
+}
+
+fn log_policy_errors(errors: &[PolicyError]) {
+    for e in errors {
+        log::error(format!("{}", e)).ok().unwrap();
+    }
+}
+
+// Applies `change`, and rolls it back with `undo` if it makes the configuration unable to
+// generate a password for a reason that did not already exist.
+fn apply_policy_change_ui(
+    config: &mut Config,
+    change: impl FnOnce(&mut Config),
+    undo: impl FnOnce(&mut Config),
+) {
+    let errors_before = config.validate_policy().err().unwrap_or_default();
+    change(config);
+    if let Err(errors) = config.validate_policy() {
+        let new_errors: Vec<PolicyError> = errors
+            .into_iter()
+            .filter(|e| !errors_before.contains(e))
+            .collect();
+        if !new_errors.is_empty() {
+            undo(config);
+            log_policy_errors(&new_errors);
+            log::warning("The change was not applied.").ok().unwrap();
+        }
+    }
+}
+
+fn save_config_ui(config: &Config) {
+    match config.save() {
+        Ok(path) => log::success(format!("Saved configuration to {}", path.display()))
+            .ok()
+            .unwrap(),
+        Err(e) => log::error(format!("Could not save configuration: {}", e))
+            .ok()
+            .unwrap(),
+    }
+}
+
+fn edit_categories_ui(config: &mut Config) {
+    loop {
+        let mut items: Vec<(usize, String, String)> = config
+            .get_all_categories()
+            .iter()
+            .enumerate()
+            .map(|(i, cat)| (i, cat.get_label(), cat.get_hint()))
+            .collect();
+
+        items.push((
+            items.len(),
+            "<- Back".to_string(),
+            "Return to the previous menu".to_string(),
+        ));
+        let choice = select(format!(
+            "Edit Categories - Entropy: {}",
+            config.get_entropy_label()
+        ))
+        .items(&items)
+        .interact()
+        .ok()
+        .unwrap();
+
+        match choice {
+            i if i < items.len() - 1 => {
+                edit_single_category_ui(config, i);
+            }
+            i if i == items.len() - 1 => {
+                break; // Back to previous menu
+            }
+            _ => continue,
+        }
+    }
+}
+
+fn edit_single_category_ui(config: &mut Config, cat_idx: usize) {
+    while let Some(cat) = config.categories.get(cat_idx) {
+        let items = vec![
+            (0, cat.get_toggle_label(), ""),
+            (
+                1,
+                "Set Minimum Characters",
+                "Set the minimum number of characters required from this category",
+            ),
+            (
+                2,
+                "Toggle Individual Code Pages",
+                "Enable or disable individual code pages within this category",
+            ),
+            (
+                3,
+                "Set Maximum Characters",
+                "Set the maximum number of characters allowed from this category, or an exact count",
+            ),
+            (
+                4,
+                "Set Weight",
+                "Set how often this category is picked in weighted sampling mode",
+            ),
+            (
+                5,
+                "Set First Position",
+                "Allow, forbid or require this category at the start of the password",
+            ),
+            (
+                6,
+                "Set Last Position",
+                "Allow, forbid or require this category at the end of the password",
+            ),
+            (7, "<- Back", "Return to the previous menu"),
+        ];
+
+        let choice = select(format!("Edit {}", cat.get_label()))
+            .items(&items)
+            .interact()
+            .ok()
+            .unwrap();
+
+        match choice {
+            0 => apply_policy_change_ui(
+                config,
+                |config| config.categories[cat_idx].toggle_enabled(),
+                |config| config.categories[cat_idx].toggle_enabled(),
+            ),
+            1 => edit_min_characters_for_category_ui(config, cat_idx),
+            2 => toggle_code_pages_ui(config, cat_idx),
+            3 => edit_max_characters_for_category_ui(config, cat_idx),
+            4 => edit_weight_for_category_ui(config, cat_idx),
+            5 => edit_position_rule_for_category_ui(
+                config,
+                cat_idx,
+                "First Position",
+                CodePointCategory::get_first_rule,
+                CodePointCategory::set_first_rule,
+            ),
+            6 => edit_position_rule_for_category_ui(
+                config,
+                cat_idx,
+                "Last Position",
+                CodePointCategory::get_last_rule,
+                CodePointCategory::set_last_rule,
+            ),
+            7 => break, // Back to previous menu
+            _ => continue,
+        }
+    }
+}
+
+fn edit_position_rule_for_category_ui(
+    config: &mut Config,
+    cat_idx: usize,
+    prompt: &str,
+    get: fn(&CodePointCategory) -> PositionRule,
+    set: fn(&mut CodePointCategory, PositionRule),
+) {
+    let items = vec![
+        (
+            PositionRule::Allowed,
+            PositionRule::Allowed.get_label(),
+            "Characters from this category may appear here",
+        ),
+        (
+            PositionRule::Forbidden,
+            PositionRule::Forbidden.get_label(),
+            "Characters from this category never appear here",
+        ),
+        (
+            PositionRule::Required,
+            PositionRule::Required.get_label(),
+            "A character from this category, or another required one, always appears here",
+        ),
+    ];
+
+    let previous = get(&config.categories[cat_idx]);
+    let rule = select(prompt)
+        .items(&items)
+        .initial_value(previous)
+        .interact()
+        .ok()
+        .unwrap();
+
+    apply_policy_change_ui(
+        config,
+        |config| set(&mut config.categories[cat_idx], rule),
+        |config| set(&mut config.categories[cat_idx], previous),
+    );
+}
+
+fn edit_min_characters_for_category_ui(config: &mut Config, cat_idx: usize) {
+    let max_allowed_for_cat = config.get_max_min_chars_for_category(cat_idx);
+    let previous_min = config.categories[cat_idx].get_min_required_chars();
+    let prompt = match max_allowed_for_cat {
+        usize::MAX => "Enter minimum required length for this category:".to_string(),
+        max => format!(
+            "Enter minimum required length for this category (between 0 and {} characters):",
+            max
+        ),
+    };
+
+    let min_chars: usize = input(prompt)
+        .placeholder(previous_min.to_string().as_str())
+        .validate(validate_usize_or_zero)
+        .validate(move |input: &String| {
+            match input.parse::<usize>().ok().unwrap() <= max_allowed_for_cat {
+                true => Ok(()),
+                false => Err(format!(
+                    "Value must be between 0 and {}, so that the minimums of the enabled categories fit in the password length and this category's maximum.",
+                    max_allowed_for_cat
+                )),
+            }
+        })
+        .interact()
+        .ok()
+        .unwrap();
+
+    apply_policy_change_ui(
+        config,
+        |config| config.categories[cat_idx].set_min_required_chars(min_chars),
+        |config| config.categories[cat_idx].set_min_required_chars(previous_min),
+    );
+}
+
+fn edit_max_characters_for_category_ui(config: &mut Config, cat_idx: usize) {
+    let cat = &config.categories[cat_idx];
+    let min_chars = cat.get_min_required_chars();
+    let previous_max = cat.get_max_allowed_chars();
+
+    let max_chars: String = input(
+        "Enter the maximum number of characters allowed from this category (equal to the minimum for an exact count, empty for no maximum):",
+    )
+    .placeholder(cat.get_pretty_max_characters().as_str())
+    .required(false)
+    .validate(move |input: &String| {
+        if input.trim().is_empty() {
+            return Ok(());
+        }
+        match input.trim().parse::<usize>() {
+            Ok(max) if max < min_chars => Err(format!(
+                "The maximum cannot be below this category's minimum of {} characters.",
+                min_chars
+            )),
+            Ok(_) => Ok(()),
+            Err(_) => Err("Please enter a number, or leave empty for no maximum.".to_string()),
+        }
+    })
+    .interact()
+    .ok()
+    .unwrap();
+    let max_chars = max_chars.trim().parse::<usize>().ok();
+
+    apply_policy_change_ui(
+        config,
+        |config| config.categories[cat_idx].set_max_allowed_chars(max_chars),
+        |config| config.categories[cat_idx].set_max_allowed_chars(previous_max),
+    );
+}
+
+fn edit_weight_for_category_ui(config: &mut Config, cat_idx: usize) {
+    let previous_weight = config.categories[cat_idx].get_weight();
+    let weight: u32 = input("Enter the weight for this category (0 to only use its minimum):")
+        .placeholder(previous_weight.to_string().as_str())
+        .validate(|input: &String| match input.parse::<u32>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err(format!(
+                "Please enter a valid number between 0 and {}.",
+                u32::MAX
+            )),
+        })
+        .interact()
+        .ok()
+        .unwrap();
+
+    apply_policy_change_ui(
+        config,
+        |config| config.categories[cat_idx].set_weight(weight),
+        |config| config.categories[cat_idx].set_weight(previous_weight),
+    );
 }
 
 fn toggle_code_pages_ui(config: &mut Config, cat_idx: usize) {
@@ -261,7 +1688,8 @@ fn toggle_code_pages_ui(config: &mut Config, cat_idx: usize) {
             .get_code_points()
             .iter()
             .enumerate()
-            .filter_map(|(i, cpc)| cpc.is_enabled().then(|| i))
+            .filter(|(_, cpc)| cpc.is_enabled())
+            .map(|(i, _)| i)
             .collect::<Vec<usize>>();
 
         let items: Vec<(usize, String, &str)> = cat
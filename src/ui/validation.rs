@@ -1,9 +1,21 @@
+#[allow(clippy::ptr_arg)]
 pub fn validate_usize(input: &String) -> Result<(), String> {
     match input.parse::<usize>() {
-        Ok(length) if length > 0 && length <= usize::MAX => Ok(()),
+        Ok(length) if length > 0 => Ok(()),
         _ => Err(format!(
             "Please enter a valid number between 1 and {}.",
             usize::MAX
         )),
     }
 }
+
+#[allow(clippy::ptr_arg)]
+pub fn validate_usize_or_zero(input: &String) -> Result<(), String> {
+    match input.parse::<usize>() {
+        Ok(_) => Ok(()),
+        _ => Err(format!(
+            "Please enter a valid number between 0 and {}.",
+            usize::MAX
+        )),
+    }
+}